@@ -0,0 +1,30 @@
+use ark_curve25519::Fr;
+use ark_std::test_rng;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use hypercube::{sumcheck::SumcheckProof, test_utils::random_mle};
+use merlin::Transcript;
+
+fn prove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sumcheck_prove");
+    group.sample_size(10);
+    let mut rng = test_rng();
+    for num_vars in [10, 15, 20] {
+        let a: Vec<Fr> = random_mle(num_vars, &mut rng);
+        let b: Vec<Fr> = random_mle(num_vars, &mut rng);
+        let claim = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_vars),
+            &num_vars,
+            |bench, _| {
+                bench.iter(|| {
+                    let mut transcript = Transcript::new(b"bench_transcript");
+                    SumcheckProof::prove(claim, vec![a.clone(), b.clone()], &mut transcript)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, prove);
+criterion_main!(benches);
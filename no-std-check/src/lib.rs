@@ -0,0 +1,75 @@
+// Links hypercube into a crate with no std at all. If anything pulls std
+// back in, its panic handler clashes with the one below and the build fails
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use ark_curve25519::Fr;
+use hypercube::{fiatshamir::new_transcript, sumcheck::SumcheckProof};
+
+// A sumcheck round trip over a product of two MLEs
+pub fn sumcheck_roundtrip() -> bool {
+    let a: Vec<Fr> = (1..=8u64).map(Fr::from).collect();
+    let b: Vec<Fr> = (1..=8u64).rev().map(Fr::from).collect();
+    let claim = a.iter().zip(&b).map(|(x, y)| *x * y).sum::<Fr>();
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut new_transcript(b"no_std"));
+    proof.verify(&mut new_transcript(b"no_std")).is_ok()
+}
+
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[cfg(not(test))]
+mod allocator {
+    use core::{
+        alloc::{GlobalAlloc, Layout},
+        cell::UnsafeCell,
+        ptr::null_mut,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    const HEAP_SIZE: usize = 1 << 20;
+
+    // Bump allocator over a static buffer; enough to link, never frees
+    struct Bump {
+        heap: UnsafeCell<[u8; HEAP_SIZE]>,
+        next: AtomicUsize,
+    }
+
+    unsafe impl Sync for Bump {}
+
+    unsafe impl GlobalAlloc for Bump {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let base = self.heap.get() as usize;
+            let mut start = 0;
+            let claimed = self
+                .next
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+                    start = (base + next).next_multiple_of(layout.align()) - base;
+                    let end = start.checked_add(layout.size())?;
+                    (end <= HEAP_SIZE).then_some(end)
+                });
+            match claimed {
+                Ok(_) => (base + start) as *mut u8,
+                Err(_) => null_mut(),
+            }
+        }
+
+        unsafe fn dealloc(&self, _: *mut u8, _: Layout) {}
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Bump = Bump {
+        heap: UnsafeCell::new([0; HEAP_SIZE]),
+        next: AtomicUsize::new(0),
+    };
+}
+
+#[test]
+fn test_sumcheck_roundtrip() {
+    assert!(sumcheck_roundtrip());
+}
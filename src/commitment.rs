@@ -0,0 +1,244 @@
+use alloc::{vec, vec::Vec};
+use ark_ec::CurveGroup;
+use ark_ff::{Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{rngs::StdRng, SeedableRng};
+use merlin::Transcript;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    multilinear::{chis, eval_chis, eval_mle},
+};
+
+// A commitment to a multilinear polynomial given by its hypercube evaluations
+// that can later be opened at any point
+pub trait PolynomialCommitment<F: PrimeField> {
+    type Commitment;
+    type OpeningProof;
+
+    fn commit(&self, evals: &[F]) -> Self::Commitment;
+    fn append_commitment(
+        &self,
+        commitment: &Self::Commitment,
+        transcript: &mut impl ProtocolTranscript<F>,
+    );
+    // Returns eval_mle(point, evals) and a proof of it
+    fn open(
+        &self,
+        evals: &[F],
+        point: &[F],
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (F, Self::OpeningProof);
+    fn verify(
+        &self,
+        commitment: &Self::Commitment,
+        point: &[F],
+        eval: F,
+        proof: &Self::OpeningProof,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> bool;
+}
+
+// Mock commitment for tests: hashes the evaluations into a field element and
+// opens by revealing all of them. Binding but neither hiding nor succinct
+pub struct HashCommitment {
+    pub label: &'static [u8],
+}
+
+impl HashCommitment {
+    fn digest<F: PrimeField>(&self, evals: &[F]) -> F {
+        let mut hasher = Transcript::new(b"hash_commitment");
+        ProtocolTranscript::<F>::append_message(&mut hasher, b"label", self.label);
+        hasher.append_points(b"evals", evals);
+        hasher.challenge_scalar(b"digest")
+    }
+}
+
+impl<F: PrimeField> PolynomialCommitment<F> for HashCommitment {
+    type Commitment = F;
+    type OpeningProof = Vec<F>;
+
+    fn commit(&self, evals: &[F]) -> F {
+        self.digest(evals)
+    }
+
+    fn append_commitment(&self, commitment: &F, transcript: &mut impl ProtocolTranscript<F>) {
+        transcript.append_scalar(b"hash_commitment", commitment);
+    }
+
+    fn open(
+        &self,
+        evals: &[F],
+        point: &[F],
+        _transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (F, Vec<F>) {
+        (eval_mle(point, evals), evals.to_vec())
+    }
+
+    fn verify(
+        &self,
+        commitment: &F,
+        point: &[F],
+        eval: F,
+        proof: &Vec<F>,
+        _transcript: &mut impl ProtocolTranscript<F>,
+    ) -> bool {
+        proof.len() == 1 << point.len()
+            && self.digest(proof) == *commitment
+            && eval_mle(point, proof) == eval
+    }
+}
+
+// Hyrax: the evaluations are laid out as a matrix with the leading half of
+// the variables indexing rows, and each row gets a Pedersen commitment. An
+// opening at (rx, ry) sends u = chis(rx)^T M, which the verifier checks
+// against the rows' commitments folded by chis(rx) before computing
+// <u, chis(ry)>. Commitments and proofs are sqrt(n) long. Not hiding
+pub struct Hyrax<G: CurveGroup> {
+    num_vars: usize,
+    generators: Vec<G::Affine>,
+}
+
+impl<G: CurveGroup> Hyrax<G> {
+    // Derives the column generators from label, so anyone can recompute
+    // them and nobody knows their discrete logs relative to each other
+    pub fn new(num_vars: usize, label: &'static [u8]) -> Self {
+        let mut hasher = Transcript::new(b"hyrax_setup");
+        hasher.append_message(b"label", label);
+        let mut seed = [0u8; 32];
+        hasher.challenge_bytes(b"seed", &mut seed);
+        let mut rng = StdRng::from_seed(seed);
+        let cols = 1 << (num_vars - num_vars / 2);
+        let generators = (0..cols).map(|_| G::rand(&mut rng)).collect::<Vec<_>>();
+        Self {
+            num_vars,
+            generators: G::normalize_batch(&generators),
+        }
+    }
+
+    fn split<'a, F>(&self, point: &'a [F]) -> (&'a [F], &'a [F]) {
+        point.split_at(self.num_vars / 2)
+    }
+}
+
+impl<G: CurveGroup> PolynomialCommitment<G::ScalarField> for Hyrax<G> {
+    type Commitment = Vec<G>;
+    type OpeningProof = Vec<G::ScalarField>;
+
+    fn commit(&self, evals: &[G::ScalarField]) -> Vec<G> {
+        assert_eq!(evals.len(), 1 << self.num_vars);
+        evals
+            .chunks(self.generators.len())
+            .map(|row| G::msm_unchecked(&self.generators, row))
+            .collect()
+    }
+
+    fn append_commitment(
+        &self,
+        commitment: &Vec<G>,
+        transcript: &mut impl ProtocolTranscript<G::ScalarField>,
+    ) {
+        let mut bytes = vec![];
+        G::normalize_batch(commitment)
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        transcript.append_message(b"hyrax_commitment", &bytes);
+    }
+
+    fn open(
+        &self,
+        evals: &[G::ScalarField],
+        point: &[G::ScalarField],
+        _transcript: &mut impl ProtocolTranscript<G::ScalarField>,
+    ) -> (G::ScalarField, Vec<G::ScalarField>) {
+        let (rx, ry) = self.split(point);
+        let cols = self.generators.len();
+        let mut u = vec![G::ScalarField::ZERO; cols];
+        for (row, l) in evals.chunks(cols).zip(chis(rx)) {
+            for (u, &m) in u.iter_mut().zip(row) {
+                *u += l * m;
+            }
+        }
+        (eval_chis(&chis(ry), &u), u)
+    }
+
+    fn verify(
+        &self,
+        commitment: &Vec<G>,
+        point: &[G::ScalarField],
+        eval: G::ScalarField,
+        proof: &Vec<G::ScalarField>,
+        _transcript: &mut impl ProtocolTranscript<G::ScalarField>,
+    ) -> bool {
+        if point.len() != self.num_vars
+            || proof.len() != self.generators.len()
+            || commitment.len() != 1 << (self.num_vars / 2)
+        {
+            return false;
+        }
+        let (rx, ry) = self.split(point);
+        let folded = G::msm_unchecked(&G::normalize_batch(commitment), &chis(rx));
+        folded == G::msm_unchecked(&self.generators, proof) && eval_chis(&chis(ry), proof) == eval
+    }
+}
+
+#[test]
+fn test_hash_commitment() {
+    use ark_curve25519::Fr;
+
+    let evals: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 * 3 + 1)).collect();
+    let point = vec![Fr::from(2), Fr::from(9), Fr::from(4)];
+    let scheme = HashCommitment { label: b"test" };
+    let commitment = scheme.commit(&evals);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (eval, proof) = scheme.open(&evals, &point, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(scheme.verify(&commitment, &point, eval, &proof, &mut vtranscript));
+    assert!(!scheme.verify(
+        &commitment,
+        &point,
+        eval + Fr::from(1),
+        &proof,
+        &mut vtranscript
+    ));
+}
+
+#[test]
+fn test_hyrax() {
+    use ark_curve25519::{EdwardsProjective, Fr};
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let evals: Vec<Fr> = (0..32).map(|_| Fr::rand(&mut rng)).collect();
+    let point: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+    let scheme = Hyrax::<EdwardsProjective>::new(5, b"test");
+    let commitment = scheme.commit(&evals);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    scheme.append_commitment(&commitment, &mut transcript);
+    let (eval, proof) = scheme.open(&evals, &point, &mut transcript);
+    assert_eq!(eval, eval_mle(&point, &evals));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    scheme.append_commitment(&commitment, &mut vtranscript);
+    assert!(scheme.verify(&commitment, &point, eval, &proof, &mut vtranscript));
+    assert!(!scheme.verify(
+        &commitment,
+        &point,
+        eval + Fr::from(1),
+        &proof,
+        &mut vtranscript
+    ));
+
+    // A proof for different evaluations does not match the commitment
+    let mut other = evals.clone();
+    other[7] += Fr::from(1);
+    let (other_eval, other_proof) = scheme.open(&other, &point, &mut transcript);
+    assert!(!scheme.verify(
+        &commitment,
+        &point,
+        other_eval,
+        &other_proof,
+        &mut vtranscript
+    ));
+}
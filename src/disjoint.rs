@@ -0,0 +1,131 @@
+use alloc::vec::Vec;
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    grandproduct::{GrandProductError, GrandProductProof},
+    multilinear::{eval_mle, Opening},
+};
+
+pub struct DisjointProof<F: PrimeField> {
+    grand_product_proof: GrandProductProof<F>,
+    a_eval: F,
+}
+
+// Proves no element of set_a equals an element of set_b by showing the
+// product of all pairwise differences a_i - b_j is nonzero. The differences
+// form an |a| x |b| table whose MLE is a(x) - b(y), so the grand product
+// reduces to one opening of each set. Both lengths must be powers of two;
+// no distinctness within a set is assumed. The prover does |a| * |b| work
+pub fn prove<F: PrimeField>(
+    set_a: &[F],
+    set_b: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> DisjointProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    let differences: Vec<F> = set_a
+        .iter()
+        .flat_map(|&a| set_b.iter().map(move |&b| a - b))
+        .collect();
+    let product = differences.iter().product();
+    let (grand_product_proof, z) = GrandProductProof::prove(&differences, product, transcript);
+    let zx = &z[..set_a.len().ilog2() as usize];
+    let a_eval = eval_mle(zx, set_a);
+    transcript.append_scalar(b"disjoint_eval", &a_eval);
+    let proof = DisjointProof {
+        grand_product_proof,
+        a_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(set_a, set_b, start);
+    proof
+}
+
+impl<F: PrimeField> DisjointProof<F> {
+    // Replays `verify` from the transcript state the prover started with and
+    // checks the openings it returns against the two sets
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, set_a: &[F], set_b: &[F], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("disjoint", || {
+            matches!(
+                verify(set_a.len(), set_b.len(), self, &mut transcript),
+                Ok(((zx, a_eval), (zy, b_eval)))
+                    if eval_mle(&zx, set_a) == a_eval && eval_mle(&zy, set_b) == b_eval
+            )
+        });
+    }
+}
+
+// Returns the openings of set_a and set_b, which the caller must check
+// against its commitments
+pub fn verify<F: PrimeField>(
+    a_len: usize,
+    b_len: usize,
+    proof: &DisjointProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Opening<F>, Opening<F>), GrandProductError> {
+    // Any nonzero product will do, so the proof's own claim is the expected
+    // one once it is known to be nonzero
+    let product = proof.grand_product_proof.claim();
+    if product.is_zero() {
+        return Err(GrandProductError::ClaimMismatch);
+    }
+    let (final_claim, z) = proof.grand_product_proof.verify(product, transcript)?;
+    transcript.append_scalar(b"disjoint_eval", &proof.a_eval);
+    // The table is |a| x |b|, so z splits into exactly a's and b's variables
+    let a_vars = a_len.ilog2() as usize;
+    if z.len() != a_vars + b_len.ilog2() as usize {
+        return Err(GrandProductError::MalformedProof);
+    }
+    let (zx, zy) = z.split_at(a_vars);
+    Ok((
+        (zx.to_vec(), proof.a_eval),
+        (zy.to_vec(), proof.a_eval - final_claim),
+    ))
+}
+
+#[test]
+fn test_disjoint() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [1, 4, 9, 16].iter().map(|&x| Fr::from(x as u64)).collect();
+    let b: Vec<Fr> = [2, 3, 5, 7, 11, 13, 17, 19]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((zx, a_eval), (zy, b_eval)) = verify(a.len(), b.len(), &proof, &mut vtranscript).unwrap();
+    assert_eq!(a_eval, eval_mle(&zx, &a));
+    assert_eq!(b_eval, eval_mle(&zy, &b));
+
+    // The proof's point does not fit a set_b of another size
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(a.len(), 4, &proof, &mut vtranscript).err(),
+        Some(GrandProductError::MalformedProof)
+    );
+}
+
+// Overlapping sets, so the proof skips self-verify
+#[test]
+fn test_overlapping() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [1, 4, 9, 16].iter().map(|&x| Fr::from(x as u64)).collect();
+    let b: Vec<Fr> = [2, 3, 5, 7, 9, 13, 17, 19]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove(&a, &b, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(a.len(), b.len(), &proof, &mut vtranscript).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+}
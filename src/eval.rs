@@ -0,0 +1,473 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    commitment::PolynomialCommitment,
+    fiatshamir::ProtocolTranscript,
+    multilinear::{chis, eval_eq, eval_mle, index_to_bits, Opening},
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+pub struct OpeningsProof<F: PrimeField> {
+    sumcheck_proof: SumcheckProof<F>,
+    eval: F,
+}
+
+fn absorb_openings<F: PrimeField>(
+    openings: &[(usize, F)],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> F {
+    for (index, value) in openings {
+        transcript.append_usize(b"opening_index", *index);
+        transcript.append_scalar(b"opening_value", value);
+    }
+    transcript.challenge_scalar(b"opening_rho")
+}
+
+// Reveals evals[i] for each requested index. The openings at the boolean
+// points bits(i) are batched with powers of rho into one sumcheck over
+// sum_x (sum_k rho^k eq(bits(i_k), x)) * evals(x), leaving a single
+// opening of evals. The caller must absorb its commitment first
+pub fn prove_openings_at_indices<F: PrimeField>(
+    evals: &[F],
+    indices: &[usize],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> OpeningsProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    let openings: Vec<(usize, F)> = indices.iter().map(|&i| (i, evals[i])).collect();
+    let rho = absorb_openings(&openings, transcript);
+    let mut weights = vec![F::ZERO; evals.len()];
+    let mut power = F::ONE;
+    let mut claim = F::ZERO;
+    for &(index, value) in openings.iter() {
+        weights[index] += power;
+        claim += power * value;
+        power *= rho;
+    }
+    let sumcheck_proof = SumcheckProof::prove(claim, vec![weights, evals.to_vec()], transcript);
+    let eval = sumcheck_proof.final_terms[1];
+    transcript.append_scalar(b"opening_eval", &eval);
+    let proof = OpeningsProof {
+        sumcheck_proof,
+        eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(evals, &openings, start);
+    proof
+}
+
+impl<F: PrimeField> OpeningsProof<F> {
+    // Replays `verify_openings_at_indices` from the transcript state the
+    // prover started with and checks the opening it returns against evals
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(
+        &self,
+        evals: &[F],
+        openings: &[(usize, F)],
+        mut transcript: impl ProtocolTranscript<F>,
+    ) {
+        crate::selfverify::check("openings", || {
+            matches!(
+                verify_openings_at_indices(evals.len(), openings, self, &mut transcript),
+                Ok((r, eval)) if eval_mle(&r, evals) == eval
+            )
+        });
+    }
+}
+
+// Checks the revealed (index, value) pairs and returns the single opening
+// of evals the caller must check against its commitment
+pub fn verify_openings_at_indices<F: PrimeField>(
+    len: usize,
+    openings: &[(usize, F)],
+    proof: &OpeningsProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<Opening<F>, SumcheckError> {
+    let num_vars = len.ilog2() as usize;
+    let rho = absorb_openings(openings, transcript);
+    let mut power = F::ONE;
+    let mut claim = F::ZERO;
+    let mut powers = vec![];
+    for &(index, value) in openings {
+        assert!(
+            index < len,
+            "opening index {index} out of range for length {len}"
+        );
+        claim += power * value;
+        powers.push(power);
+        power *= rho;
+    }
+    if proof.sumcheck_proof.claim != claim {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    let (r, expected) = proof.sumcheck_proof.verify_shape(num_vars, 2, transcript)?;
+    let weight: F = openings
+        .iter()
+        .zip(powers)
+        .map(|(&(index, _), power)| power * eval_eq(&index_to_bits(index, num_vars), &r))
+        .sum();
+    if expected != weight * proof.eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"opening_eval", &proof.eval);
+    Ok((r, proof.eval))
+}
+
+pub struct ConcatProof<F: PrimeField> {
+    a_eval: F,
+    b_eval: F,
+}
+
+// Proves c == a ++ b. With the high bit t of c's index as selector, c's MLE
+// is (1 - t) * a(x) + t * b(x), so checking that identity at a random point
+// (t, r) suffices and no sumcheck is needed. The caller must absorb its
+// commitments first
+pub fn prove_concat<F: PrimeField>(
+    a: &[F],
+    b: &[F],
+    c: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> ConcatProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(a.len(), b.len());
+    assert_eq!(c.len(), 2 * a.len());
+    transcript.append_version();
+    let r = transcript.challenge_scalars(b"concat_r", a.len().ilog2() as usize);
+    let (a_eval, b_eval) = (eval_mle(&r, a), eval_mle(&r, b));
+    transcript.append_scalar(b"concat_eval", &a_eval);
+    transcript.append_scalar(b"concat_eval", &b_eval);
+    // Only the verifier uses t, but drawing it keeps the transcripts in sync
+    let _t: F = transcript.challenge_scalar(b"concat_t");
+    let proof = ConcatProof { a_eval, b_eval };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify([a, b, c], start);
+    proof
+}
+
+impl<F: PrimeField> ConcatProof<F> {
+    // Replays `verify_concat` from the transcript state the prover started
+    // with and checks the openings it returns against a, b and c; a c that
+    // is not a ++ b fails here
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, tables: [&[F]; 3], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("concat", || {
+            let (a, b, c) = verify_concat(tables[0].len(), self, &mut transcript);
+            [a, b, c]
+                .iter()
+                .zip(tables)
+                .all(|((point, eval), table)| eval_mle(point, table) == *eval)
+        });
+    }
+}
+
+// Returns the openings of a, b, and c, which the caller must check against
+// its commitments
+pub fn verify_concat<F: PrimeField>(
+    len: usize,
+    proof: &ConcatProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> (Opening<F>, Opening<F>, Opening<F>) {
+    transcript.append_version();
+    let r = transcript.challenge_scalars(b"concat_r", len.ilog2() as usize);
+    transcript.append_scalar(b"concat_eval", &proof.a_eval);
+    transcript.append_scalar(b"concat_eval", &proof.b_eval);
+    let t = transcript.challenge_scalar(b"concat_t");
+    let c_eval = (F::ONE - t) * proof.a_eval + t * proof.b_eval;
+    let c_point = core::iter::once(t).chain(r.iter().cloned()).collect();
+    (
+        (r.clone(), proof.a_eval),
+        (r, proof.b_eval),
+        (c_point, c_eval),
+    )
+}
+
+pub struct CrossCommitmentProof<F, A, B> {
+    eval: F,
+    proof_a: A,
+    proof_b: B,
+}
+
+// Proves commit_a (under scheme A) and commit_b (under scheme B) are
+// commitments to the same polynomial by opening both at one random point.
+// Two distinct multilinear polynomials agree there with probability at most
+// num_vars / |F|
+pub fn prove_cross_commitment<F, A, B>(
+    scheme_a: &A,
+    scheme_b: &B,
+    evals: &[F],
+    commit_a: &A::Commitment,
+    commit_b: &B::Commitment,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> CrossCommitmentProof<F, A::OpeningProof, B::OpeningProof>
+where
+    F: PrimeField,
+    A: PolynomialCommitment<F>,
+    B: PolynomialCommitment<F>,
+{
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    transcript.append_version();
+    scheme_a.append_commitment(commit_a, transcript);
+    scheme_b.append_commitment(commit_b, transcript);
+    let r = transcript.challenge_scalars(b"cross_commitment_r", evals.len().ilog2() as usize);
+    let (eval, proof_a) = scheme_a.open(evals, &r, transcript);
+    let (_, proof_b) = scheme_b.open(evals, &r, transcript);
+    let proof = CrossCommitmentProof {
+        eval,
+        proof_a,
+        proof_b,
+    };
+    // Replays `verify_cross_commitment` from the transcript state the prover
+    // started with
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    crate::selfverify::check("cross commitment", || {
+        let mut transcript = start;
+        verify_cross_commitment(
+            scheme_a,
+            scheme_b,
+            evals.len().ilog2() as usize,
+            commit_a,
+            commit_b,
+            &proof,
+            &mut transcript,
+        )
+    });
+    proof
+}
+
+pub fn verify_cross_commitment<F, A, B>(
+    scheme_a: &A,
+    scheme_b: &B,
+    num_vars: usize,
+    commit_a: &A::Commitment,
+    commit_b: &B::Commitment,
+    proof: &CrossCommitmentProof<F, A::OpeningProof, B::OpeningProof>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> bool
+where
+    F: PrimeField,
+    A: PolynomialCommitment<F>,
+    B: PolynomialCommitment<F>,
+{
+    transcript.append_version();
+    scheme_a.append_commitment(commit_a, transcript);
+    scheme_b.append_commitment(commit_b, transcript);
+    let r = transcript.challenge_scalars(b"cross_commitment_r", num_vars);
+    scheme_a.verify(commit_a, &r, proof.eval, &proof.proof_a, transcript)
+        && scheme_b.verify(commit_b, &r, proof.eval, &proof.proof_b, transcript)
+}
+
+pub struct ConstantProof<F: PrimeField> {
+    sumcheck_proof: SumcheckProof<F>,
+    eval: F,
+}
+
+// Proves every entry of a equals the public c with an eq-weighted zero check
+// of a(x) - c at a random point. The caller must absorb its commitment first
+pub fn prove_constant<F: PrimeField>(
+    a: &[F],
+    c: F,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> ConstantProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    transcript.append_scalar(b"constant_value", &c);
+    let rz = transcript.challenge_scalars(b"constant_rz", a.len().ilog2() as usize);
+    let shifted = a.iter().map(|&x| x - c).collect();
+    let sumcheck_proof = SumcheckProof::prove(F::ZERO, vec![chis(&rz), shifted], transcript);
+    let eval = sumcheck_proof.final_terms[1] + c;
+    transcript.append_scalar(b"constant_eval", &eval);
+    let proof = ConstantProof {
+        sumcheck_proof,
+        eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(a, c, start);
+    proof
+}
+
+impl<F: PrimeField> ConstantProof<F> {
+    // Replays `verify_constant` from the transcript state the prover started
+    // with and checks the opening it returns against a
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, a: &[F], c: F, mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("constant", || {
+            matches!(
+                verify_constant(a.len(), c, self, &mut transcript),
+                Ok((r, eval)) if eval_mle(&r, a) == eval
+            )
+        });
+    }
+}
+
+// Returns the opening of a, which the caller must check against its
+// commitment
+pub fn verify_constant<F: PrimeField>(
+    len: usize,
+    c: F,
+    proof: &ConstantProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<Opening<F>, SumcheckError> {
+    let num_vars = len.ilog2() as usize;
+    transcript.append_scalar(b"constant_value", &c);
+    let rz = transcript.challenge_scalars(b"constant_rz", num_vars);
+    // a - c must vanish everywhere, so its eq-weighted sum is zero
+    if proof.sumcheck_proof.claim != F::ZERO {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    let (r, expected) = proof.sumcheck_proof.verify_shape(num_vars, 2, transcript)?;
+    if expected != eval_eq(&r, &rz) * (proof.eval - c) {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"constant_eval", &proof.eval);
+    Ok((r, proof.eval))
+}
+
+#[test]
+fn test_openings_at_indices() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let evals: Vec<Fr> = (0..16).map(|i| Fr::from((i * i + 3) as u64)).collect();
+    let indices = [2, 7, 13];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_openings_at_indices(&evals, &indices, &mut transcript);
+
+    let openings: Vec<(usize, Fr)> = indices.iter().map(|&i| (i, evals[i])).collect();
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (r, eval) =
+        verify_openings_at_indices(evals.len(), &openings, &proof, &mut vtranscript).unwrap();
+    assert_eq!(eval, eval_mle(&r, &evals));
+}
+
+#[test]
+fn test_openings_at_indices_wrong_value() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let evals: Vec<Fr> = (0..16).map(|i| Fr::from((i * i + 3) as u64)).collect();
+    let indices = [2, 7, 13];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_openings_at_indices(&evals, &indices, &mut transcript);
+
+    let mut openings: Vec<(usize, Fr)> = indices.iter().map(|&i| (i, evals[i])).collect();
+    openings[1].1 += Fr::from(1);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_openings_at_indices(evals.len(), &openings, &proof, &mut vtranscript),
+        Err(SumcheckError::UnexpectedClaim)
+    );
+}
+
+#[test]
+fn test_concat() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(100 - i as u64)).collect();
+    let c: Vec<Fr> = a.iter().chain(&b).cloned().collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_concat(&a, &b, &c, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((ra, a_eval), (rb, b_eval), (rc, c_eval)) =
+        verify_concat(a.len(), &proof, &mut vtranscript);
+    assert_eq!(a_eval, eval_mle(&ra, &a));
+    assert_eq!(b_eval, eval_mle(&rb, &b));
+    assert_eq!(c_eval, eval_mle(&rc, &c));
+
+    let swapped: Vec<Fr> = b.iter().chain(&a).cloned().collect();
+    assert_ne!(c_eval, eval_mle(&rc, &swapped));
+}
+
+#[test]
+fn test_cross_commitment() {
+    use crate::commitment::HashCommitment;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let evals: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 11)).collect();
+    let other: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 12)).collect();
+    let scheme_a = HashCommitment { label: b"scheme_a" };
+    let scheme_b = HashCommitment { label: b"scheme_b" };
+    let commit_a = scheme_a.commit(&evals);
+    let commit_b = scheme_b.commit(&evals);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_cross_commitment(
+        &scheme_a,
+        &scheme_b,
+        &evals,
+        &commit_a,
+        &commit_b,
+        &mut transcript,
+    );
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(verify_cross_commitment(
+        &scheme_a,
+        &scheme_b,
+        3,
+        &commit_a,
+        &commit_b,
+        &proof,
+        &mut vtranscript
+    ));
+
+    // Commitments to different polynomials cannot be shown equal, so the
+    // proof skips self-verify
+    let commit_other = scheme_b.commit(&other);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| {
+        prove_cross_commitment(
+            &scheme_a,
+            &scheme_b,
+            &evals,
+            &commit_a,
+            &commit_other,
+            &mut transcript,
+        )
+    });
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(!verify_cross_commitment(
+        &scheme_a,
+        &scheme_b,
+        3,
+        &commit_a,
+        &commit_other,
+        &proof,
+        &mut vtranscript
+    ));
+}
+
+#[test]
+fn test_constant() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a = vec![Fr::from(42); 8];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_constant(&a, Fr::from(42), &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (r, eval) = verify_constant(a.len(), Fr::from(42), &proof, &mut vtranscript).unwrap();
+    assert_eq!(eval, eval_mle(&r, &a));
+}
+
+// A dishonest prover, so its proof skips self-verify
+#[test]
+fn test_not_constant() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let mut a = vec![Fr::from(42); 8];
+    a[5] = Fr::from(41);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_constant(&a, Fr::from(42), &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_constant(a.len(), Fr::from(42), &proof, &mut vtranscript),
+        Err(SumcheckError::FinalEvalMismatch)
+    );
+}
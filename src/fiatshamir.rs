@@ -1,12 +1,78 @@
-use ark_ff::PrimeField;
+use alloc::{vec, vec::Vec};
+use ark_ff::{BigInteger, PrimeField};
 use merlin::Transcript;
 
-pub trait ProtocolTranscript<F: PrimeField> {
+// Bumped whenever the bytes the transcript absorbs change, so proofs made
+// under one layout never verify under another
+pub const TRANSCRIPT_VERSION: u64 = 3;
+
+// Starts a protocol transcript under `domain`, for callers that do not
+// depend on merlin themselves
+pub fn new_transcript(domain: &'static [u8]) -> Transcript {
+    Transcript::new(domain)
+}
+
+pub trait ProtocolTranscript<F: PrimeField>: Clone {
     fn append_scalar(&mut self, label: &'static [u8], scalar: &F);
-    fn append_message(&mut self, label: &'static [u8], message: &'static [u8]);
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
     fn append_points(&mut self, label: &'static [u8], points: &[F]);
+    // Absorbs an integer's little-endian bytes, for sizes and counts that
+    // need not pass through the field
+    fn append_u64(&mut self, label: &'static [u8], value: u64);
+    fn append_usize(&mut self, label: &'static [u8], value: usize);
+    // Absorbs TRANSCRIPT_VERSION. Every protocol does so before its own
+    // header, so prover and verifier bind the version whatever transcript
+    // the caller hands them
+    fn append_version(&mut self) {
+        self.append_u64(b"transcript_version", TRANSCRIPT_VERSION);
+    }
+    // Absorbs the whole slice as one length-prefixed message, which is much
+    // cheaper than `append_points` for long vectors. The two produce
+    // different transcripts, so prover and verifier must use the same one
+    fn append_scalars_packed(&mut self, label: &'static [u8], scalars: &[F]);
     fn challenge_scalar(&mut self, label: &'static [u8]) -> F;
     fn challenge_scalars(&mut self, label: &'static [u8], count: usize) -> Vec<F>;
+    // Samples exactly uniformly by drawing modulus-width values and
+    // rejecting those past the modulus. `challenge_scalar` reduces 512 bits
+    // mod p, whose bias is negligible for fields of a few hundred bits; use
+    // this one when the field is small enough for the bias to matter
+    fn challenge_scalar_unbiased(&mut self, label: &'static [u8]) -> F;
+    // Like `challenge_scalar`, but re-squeezes with a retry counter absorbed
+    // while the result is zero, for challenges that get inverted or raised
+    // to powers. Agrees with `challenge_scalar` whenever that is nonzero
+    fn challenge_scalar_nonzero(&mut self, label: &'static [u8]) -> F;
+    // Starts a sub-transcript whose challenges depend only on the parent's
+    // state at this call plus the sub-protocol's own absorptions, so it can
+    // be verified in isolation from a clone of the returned state
+    fn begin_subprotocol(&mut self, label: &'static [u8]) -> Self
+    where
+        Self: Sized;
+    // Binds the parent to everything absorbed by the finished sub-transcript
+    fn end_subprotocol(&mut self, label: &'static [u8], sub: Self)
+    where
+        Self: Sized;
+    // Copies the current state under a domain separator so independent
+    // subproofs can run in parallel. Forks must be taken from the same parent
+    // state with distinct labels, the parent must not absorb or squeeze until
+    // they are merged, and they must be merged in a fixed order the verifier
+    // repeats
+    fn fork(&self, label: &'static [u8]) -> Self
+    where
+        Self: Sized;
+    // Absorbs a finished fork's final challenge back into the parent
+    fn merge(&mut self, label: &'static [u8], child: Self)
+    where
+        Self: Sized;
+    // Saves the current state. Rolling back is assigning the saved copy back
+    fn checkpoint(&self) -> Self
+    where
+        Self: Sized;
+    // Runs f on a copy of the transcript and keeps its absorptions and
+    // squeezes only if it returns Some, for speculative derivations such as
+    // retrying a degenerate challenge
+    fn with_checkpoint<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T>
+    where
+        Self: Sized;
 }
 
 impl<F: PrimeField> ProtocolTranscript<F> for Transcript {
@@ -16,11 +82,14 @@ impl<F: PrimeField> ProtocolTranscript<F> for Transcript {
         self.append_message(label, &buf);
     }
 
-    fn append_message(&mut self, label: &'static [u8], msg: &'static [u8]) {
+    fn append_message(&mut self, label: &'static [u8], msg: &[u8]) {
         self.append_message(label, msg);
     }
 
     fn append_points(&mut self, label: &'static [u8], points: &[F]) {
+        // The length frames the points so vectors of different lengths
+        // under one label never produce related transcripts
+        ProtocolTranscript::<F>::append_usize(self, label, points.len());
         self.append_message(label, b"begin_append_points");
         for item in points.iter() {
             self.append_scalar(label, item);
@@ -28,6 +97,22 @@ impl<F: PrimeField> ProtocolTranscript<F> for Transcript {
         self.append_message(label, b"end_append_points");
     }
 
+    fn append_u64(&mut self, label: &'static [u8], value: u64) {
+        self.append_message(label, &value.to_le_bytes());
+    }
+
+    fn append_usize(&mut self, label: &'static [u8], value: usize) {
+        ProtocolTranscript::<F>::append_u64(self, label, value as u64);
+    }
+
+    fn append_scalars_packed(&mut self, label: &'static [u8], scalars: &[F]) {
+        let mut buf = (scalars.len() as u64).to_le_bytes().to_vec();
+        for scalar in scalars {
+            scalar.serialize_compressed(&mut buf).unwrap();
+        }
+        self.append_message(label, &buf);
+    }
+
     fn challenge_scalar(&mut self, label: &'static [u8]) -> F {
         let mut buf = [0u8; 64];
         self.challenge_bytes(label, &mut buf);
@@ -37,9 +122,473 @@ impl<F: PrimeField> ProtocolTranscript<F> for Transcript {
     fn challenge_scalars(&mut self, label: &'static [u8], count: usize) -> Vec<F> {
         (0..count).map(|_| self.challenge_scalar(label)).collect()
     }
+
+    fn challenge_scalar_nonzero(&mut self, label: &'static [u8]) -> F {
+        let mut scalar: F = self.challenge_scalar(label);
+        let mut retry = 0u64;
+        while scalar.is_zero() {
+            retry += 1;
+            self.append_message(b"nonzero_retry", &retry.to_le_bytes());
+            scalar = self.challenge_scalar(label);
+        }
+        scalar
+    }
+
+    fn challenge_scalar_unbiased(&mut self, label: &'static [u8]) -> F {
+        let bits = F::MODULUS_BIT_SIZE as usize;
+        let mut buf = vec![0u8; bits.div_ceil(8)];
+        loop {
+            self.challenge_bytes(label, &mut buf);
+            let le_bits: Vec<bool> = (0..bits)
+                .map(|i| (buf[i / 8] >> (i % 8)) & 1 == 1)
+                .collect();
+            if let Some(scalar) = F::from_bigint(F::BigInt::from_bits_le(&le_bits)) {
+                return scalar;
+            }
+        }
+    }
+
+    fn begin_subprotocol(&mut self, label: &'static [u8]) -> Self {
+        let mut seed = [0u8; 32];
+        self.challenge_bytes(label, &mut seed);
+        let mut sub = Transcript::new(b"subprotocol");
+        sub.append_message(b"subprotocol_label", label);
+        sub.append_message(b"subprotocol_seed", &seed);
+        sub
+    }
+
+    fn end_subprotocol(&mut self, label: &'static [u8], mut sub: Self) {
+        let mut digest = [0u8; 32];
+        sub.challenge_bytes(b"subprotocol_digest", &mut digest);
+        self.append_message(label, &digest);
+    }
+
+    fn fork(&self, label: &'static [u8]) -> Self {
+        let mut child = self.clone();
+        child.append_message(b"fork", label);
+        child
+    }
+
+    fn merge(&mut self, label: &'static [u8], mut child: Self) {
+        let mut digest = [0u8; 32];
+        child.challenge_bytes(b"fork_digest", &mut digest);
+        self.append_message(label, &digest);
+    }
+
+    fn checkpoint(&self) -> Self {
+        self.clone()
+    }
+
+    fn with_checkpoint<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let mut branch = self.clone();
+        let result = f(&mut branch)?;
+        *self = branch;
+        Some(result)
+    }
+}
+
+// Replays raw absorptions into a fresh transcript started with `domain` and
+// returns the challenge drawn next under `label`. Useful as a ground truth
+// when publishing test vectors
+pub fn challenge_after<F: PrimeField>(
+    domain: &'static [u8],
+    absorptions: &[(&'static [u8], Vec<u8>)],
+    label: &'static [u8],
+) -> F {
+    let mut transcript = Transcript::new(domain);
+    for (message_label, message) in absorptions {
+        transcript.append_message(message_label, message);
+    }
+    transcript.challenge_scalar(label)
 }
 
-trait Provable<F: PrimeField> {
-    fn prove(&self, transcript: impl ProtocolTranscript<F>);
-    fn verify(&self, transcript: impl ProtocolTranscript<F>) -> bool;
+// A uniform entry point over proof systems. The statement is the public
+// claim both sides know, the witness is the prover's private input, and
+// the output is what a successful verification leaves for the caller to
+// check against its commitments
+pub trait Provable<F: PrimeField> {
+    type Statement;
+    type Witness;
+    type Proof;
+    type Output;
+
+    fn prove(
+        statement: &Self::Statement,
+        witness: Self::Witness,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self::Proof;
+
+    fn verify(
+        statement: &Self::Statement,
+        proof: &Self::Proof,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Option<Self::Output>;
+}
+
+#[test]
+fn test_subprotocol() {
+    use crate::sumcheck::SumcheckProof;
+    use ark_curve25519::Fr;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(3 * i as u64 + 2)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    ProtocolTranscript::<Fr>::append_scalar(&mut transcript, b"outer", &Fr::from(7));
+    let mut sub = ProtocolTranscript::<Fr>::begin_subprotocol(&mut transcript, b"sub");
+    let forked = sub.clone();
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut sub);
+    ProtocolTranscript::<Fr>::end_subprotocol(&mut transcript, b"sub", sub);
+
+    // Only the forked state is needed to verify the sub-proof
+    let mut vsub = forked;
+    proof.verify_unchecked(&mut vsub);
+
+    // Forks agree exactly when the parents agreed at begin_subprotocol
+    let fork = |outer: u64| {
+        let mut parent = Transcript::new(b"test_transcript");
+        ProtocolTranscript::<Fr>::append_scalar(&mut parent, b"outer", &Fr::from(outer));
+        let mut sub = ProtocolTranscript::<Fr>::begin_subprotocol(&mut parent, b"sub");
+        ProtocolTranscript::<Fr>::append_scalar(&mut parent, b"later", &Fr::from(1));
+        ProtocolTranscript::<Fr>::challenge_scalar(&mut sub, b"c")
+    };
+    assert_eq!(fork(7), fork(7));
+    assert_ne!(fork(7), fork(8));
+}
+
+#[test]
+fn test_challenge_after() {
+    use crate::sumcheck::SumcheckProof;
+    use ark_curve25519::Fr;
+    use ark_serialize::CanonicalSerialize;
+
+    let a: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..4).map(|i| Fr::from(5 * i as u64 + 2)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+
+    let bytes = |x: Fr| {
+        let mut buf = vec![];
+        x.serialize_compressed(&mut buf).unwrap();
+        buf
+    };
+    let mut absorptions: Vec<(&'static [u8], Vec<u8>)> = vec![
+        (
+            b"transcript_version",
+            TRANSCRIPT_VERSION.to_le_bytes().to_vec(),
+        ),
+        (b"sumcheck_claim", bytes(claim)),
+        (b"sumcheck_degree", 2u64.to_le_bytes().to_vec()),
+        (b"sumcheck_rounds", 2u64.to_le_bytes().to_vec()),
+        (b"sumcheck_points", 3u64.to_le_bytes().to_vec()),
+        (b"sumcheck_points", b"begin_append_points".to_vec()),
+    ];
+    for &point in proof.polynomials[0].iter() {
+        absorptions.push((b"sumcheck_points", bytes(point)));
+    }
+    absorptions.push((b"sumcheck_points", b"end_append_points".to_vec()));
+
+    let expected: Fr = challenge_after(b"test_transcript", &absorptions, b"sumcheck_challenge");
+    assert_eq!(expected, proof.rands[0]);
+}
+
+#[test]
+fn test_append_runtime_message() {
+    use ark_curve25519::Fr;
+
+    let bytes: Vec<u8> = (0..32u8).map(|i| i * 3).collect();
+    let mut a = Transcript::new(b"test_transcript");
+    ProtocolTranscript::<Fr>::append_message(&mut a, b"bytes", &bytes);
+    let mut b = Transcript::new(b"test_transcript");
+    ProtocolTranscript::<Fr>::append_message(&mut b, b"bytes", &bytes[..31]);
+    let ca: Fr = a.challenge_scalar(b"c");
+    let cb: Fr = b.challenge_scalar(b"c");
+    assert_ne!(ca, cb);
+}
+
+#[test]
+fn test_fork() {
+    use ark_curve25519::Fr;
+
+    let run = || {
+        let mut parent = Transcript::new(b"test_transcript");
+        ProtocolTranscript::<Fr>::append_scalar(&mut parent, b"outer", &Fr::from(3));
+        let mut rows = ProtocolTranscript::<Fr>::fork(&parent, b"rows");
+        let mut cols = ProtocolTranscript::<Fr>::fork(&parent, b"cols");
+        let row_challenge: Fr = rows.challenge_scalar(b"c");
+        let col_challenge: Fr = cols.challenge_scalar(b"c");
+        ProtocolTranscript::<Fr>::merge(&mut parent, b"rows", rows);
+        ProtocolTranscript::<Fr>::merge(&mut parent, b"cols", cols);
+        let after: Fr = parent.challenge_scalar(b"c");
+        (row_challenge, col_challenge, after)
+    };
+    let (row_challenge, col_challenge, after) = run();
+    assert_ne!(row_challenge, col_challenge);
+    assert_eq!(run(), (row_challenge, col_challenge, after));
+}
+
+// A 7-bit field where reducing a small buffer mod p would be visibly biased.
+// The derive expands to impls inside a helper const, hence the allow
+#[cfg(test)]
+#[allow(non_local_definitions)]
+pub(crate) mod small_field {
+    use ark_ff::{Fp2, Fp2Config, MontFp};
+
+    #[derive(ark_ff::MontConfig)]
+    #[modulus = "97"]
+    #[generator = "5"]
+    pub struct SmallConfig;
+
+    pub type Small = ark_ff::Fp64<ark_ff::MontBackend<SmallConfig, 1>>;
+
+    // F_97[u] / (u^2 - 5), with 5 a non-residue since it generates F_97*
+    pub struct SmallExtConfig;
+
+    impl Fp2Config for SmallExtConfig {
+        type Fp = Small;
+        const NONRESIDUE: Small = MontFp!("5");
+        const FROBENIUS_COEFF_FP2_C1: &'static [Small] = &[MontFp!("1"), MontFp!("96")];
+    }
+
+    pub type SmallExt = Fp2<SmallExtConfig>;
+}
+
+#[test]
+fn test_challenge_scalar_unbiased() {
+    use small_field::Small;
+
+    let draws = 97 * 200;
+    let mut counts = [0usize; 97];
+    let mut transcript = Transcript::new(b"test_transcript");
+    for _ in 0..draws {
+        let c: Small = transcript.challenge_scalar_unbiased(b"c");
+        counts[c.into_bigint().as_ref()[0] as usize] += 1;
+    }
+    // Chi-squared with 96 degrees of freedom; 150 is far in the tail (p < 0.001)
+    let expected = (draws / 97) as f64;
+    let chi2: f64 = counts
+        .iter()
+        .map(|&n| (n as f64 - expected).powi(2) / expected)
+        .sum();
+    assert!(chi2 < 150.0, "chi-squared {} too large", chi2);
+}
+
+#[test]
+fn test_challenge_scalar_nonzero() {
+    use ark_ff::Zero;
+    use small_field::Small;
+
+    let seeded = |seed: u64| {
+        let mut transcript = Transcript::new(b"test_transcript");
+        ProtocolTranscript::<Small>::append_message(&mut transcript, b"seed", &seed.to_le_bytes());
+        transcript
+    };
+    // In a 97-element field some early seed squeezes zero
+    let seed = (0..10_000u64)
+        .find(|&seed| {
+            ProtocolTranscript::<Small>::challenge_scalar(&mut seeded(seed), b"c").is_zero()
+        })
+        .unwrap();
+    let c: Small = seeded(seed).challenge_scalar_nonzero(b"c");
+    assert!(!c.is_zero());
+
+    // Where the plain squeeze is nonzero the two agree
+    let other = (0..10_000u64)
+        .find(|&seed| {
+            !ProtocolTranscript::<Small>::challenge_scalar(&mut seeded(seed), b"c").is_zero()
+        })
+        .unwrap();
+    let plain: Small = seeded(other).challenge_scalar(b"c");
+    let nonzero: Small = seeded(other).challenge_scalar_nonzero(b"c");
+    assert_eq!(nonzero, plain);
+}
+
+#[test]
+fn test_append_scalars_packed() {
+    use ark_curve25519::Fr;
+
+    let scalars: Vec<Fr> = (0..100).map(|i| Fr::from(i as u64 * 31 + 7)).collect();
+    let prover = {
+        let mut t = Transcript::new(b"test_transcript");
+        t.append_scalars_packed(b"points", &scalars);
+        ProtocolTranscript::<Fr>::challenge_scalar(&mut t, b"c")
+    };
+    let verifier = {
+        let mut t = Transcript::new(b"test_transcript");
+        t.append_scalars_packed(b"points", &scalars);
+        ProtocolTranscript::<Fr>::challenge_scalar(&mut t, b"c")
+    };
+    assert_eq!(prover, verifier);
+
+    let mut t = Transcript::new(b"test_transcript");
+    t.append_points(b"points", &scalars);
+    let unpacked: Fr = t.challenge_scalar(b"c");
+    assert_ne!(prover, unpacked);
+
+    let mut t = Transcript::new(b"test_transcript");
+    t.append_scalars_packed(b"points", &scalars[..99]);
+    let shorter: Fr = t.challenge_scalar(b"c");
+    assert_ne!(prover, shorter);
+}
+
+#[test]
+fn test_provable() {
+    use crate::{grandproduct::GrandProductProof, multilinear::eval_mle, sumcheck::SumcheckProof};
+    use ark_curve25519::Fr;
+
+    fn roundtrip<P: Provable<Fr>>(
+        statement: P::Statement,
+        witness: P::Witness,
+    ) -> Option<P::Output> {
+        let mut transcript = Transcript::new(b"test_transcript");
+        let proof = P::prove(&statement, witness, &mut transcript);
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        P::verify(&statement, &proof, &mut vtranscript)
+    }
+
+    let a: Vec<Fr> = (1..=8).map(|i| Fr::from(i as u64)).collect();
+    let b: Vec<Fr> = (1..=8).map(|i| Fr::from(2 * i as u64)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let (point, eval) = roundtrip::<SumcheckProof<Fr>>(claim, vec![a.clone(), b.clone()]).unwrap();
+    assert_eq!(eval, eval_mle(&point, &a) * eval_mle(&point, &b));
+
+    let product: Fr = a.iter().product();
+    let (final_claim, z) = roundtrip::<GrandProductProof<Fr>>(product, a.clone()).unwrap();
+    assert_eq!(final_claim, eval_mle(&z, &a));
+}
+
+#[test]
+fn test_append_u64() {
+    use ark_curve25519::Fr;
+
+    let challenge = |absorb: bool| -> Fr {
+        let mut transcript = Transcript::new(b"test_transcript");
+        if absorb {
+            ProtocolTranscript::<Fr>::append_u64(&mut transcript, b"memory", 16);
+        }
+        transcript.challenge_scalar(b"c")
+    };
+    // A verifier that skips absorbing a size the prover absorbed diverges
+    assert_ne!(challenge(true), challenge(false));
+    assert_eq!(challenge(true), challenge(true));
+
+    let mut a = Transcript::new(b"test_transcript");
+    let mut b = a.clone();
+    ProtocolTranscript::<Fr>::append_usize(&mut a, b"n", 7);
+    ProtocolTranscript::<Fr>::append_u64(&mut b, b"n", 7);
+    let (ca, cb): (Fr, Fr) = (a.challenge_scalar(b"c"), b.challenge_scalar(b"c"));
+    assert_eq!(ca, cb);
+}
+
+#[test]
+fn test_with_checkpoint() {
+    use ark_curve25519::Fr;
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let saved = ProtocolTranscript::<Fr>::checkpoint(&transcript);
+    let rejected = ProtocolTranscript::<Fr>::with_checkpoint(&mut transcript, |t| {
+        t.append_scalar(b"x", &Fr::from(1u64));
+        let _: Fr = t.challenge_scalar(b"c");
+        None::<()>
+    });
+    assert!(rejected.is_none());
+    let mut restored = saved.clone();
+    let c: Fr = transcript.challenge_scalar(b"next");
+    let expected: Fr = restored.challenge_scalar(b"next");
+    assert_eq!(c, expected);
+
+    // An accepted branch is committed
+    let mut transcript = saved.clone();
+    let accepted = ProtocolTranscript::<Fr>::with_checkpoint(&mut transcript, |t| {
+        t.append_scalar(b"x", &Fr::from(1u64));
+        Some(t.challenge_scalar(b"c"))
+    });
+    let mut replay = saved;
+    replay.append_scalar(b"x", &Fr::from(1u64));
+    let replayed: Fr = replay.challenge_scalar(b"c");
+    assert_eq!(accepted, Some(replayed));
+    let (a, b): (Fr, Fr) = (
+        transcript.challenge_scalar(b"next"),
+        replay.challenge_scalar(b"next"),
+    );
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_append_points_length() {
+    use ark_curve25519::Fr;
+
+    let [x, y, z] = [3u64, 5, 7].map(Fr::from);
+    let challenge = |calls: &[&[Fr]]| -> Fr {
+        let mut transcript = Transcript::new(b"test_transcript");
+        for points in calls {
+            transcript.append_points(b"points", points);
+        }
+        transcript.challenge_scalar(b"c")
+    };
+    assert_ne!(challenge(&[&[x], &[y, z]]), challenge(&[&[x, y], &[z]]));
+    assert_eq!(challenge(&[&[x], &[y, z]]), challenge(&[&[x], &[y, z]]));
+
+    // Raw messages under the same label reproduce every absorption of a
+    // frame except its length, so only the length tells the two apart
+    let mut framed = Transcript::new(b"test_transcript");
+    framed.append_points(b"points", &[x, y]);
+    let mut forged = Transcript::new(b"test_transcript");
+    ProtocolTranscript::<Fr>::append_message(&mut forged, b"points", b"begin_append_points");
+    for point in [x, y] {
+        forged.append_scalar(b"points", &point);
+    }
+    ProtocolTranscript::<Fr>::append_message(&mut forged, b"points", b"end_append_points");
+    let framed: Fr = framed.challenge_scalar(b"c");
+    let forged: Fr = forged.challenge_scalar(b"c");
+    assert_ne!(framed, forged);
+}
+
+#[test]
+fn test_version_absorbed() {
+    use crate::{multilinear::eval_mle, sumcheck::SumcheckProof};
+    use ark_curve25519::Fr;
+    use ark_serialize::CanonicalSerialize;
+
+    // One round, so the only challenge is the one the final terms fold at
+    let a = vec![Fr::from(3u64), Fr::from(5u64)];
+    let b = vec![Fr::from(7u64), Fr::from(2u64)];
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let versioned = SumcheckProof::prove(
+        claim,
+        vec![a.clone(), b.clone()],
+        &mut new_transcript(b"test"),
+    );
+
+    // The same proof from a prover whose transcript never saw the version
+    let bytes = |x: Fr| {
+        let mut buf = vec![];
+        x.serialize_compressed(&mut buf).unwrap();
+        buf
+    };
+    let mut absorptions: Vec<(&'static [u8], Vec<u8>)> = vec![
+        (b"sumcheck_claim", bytes(claim)),
+        (b"sumcheck_degree", 2u64.to_le_bytes().to_vec()),
+        (b"sumcheck_rounds", 1u64.to_le_bytes().to_vec()),
+        (b"sumcheck_points", 3u64.to_le_bytes().to_vec()),
+        (b"sumcheck_points", b"begin_append_points".to_vec()),
+    ];
+    for &point in versioned.polynomials[0].iter() {
+        absorptions.push((b"sumcheck_points", bytes(point)));
+    }
+    absorptions.push((b"sumcheck_points", b"end_append_points".to_vec()));
+    let r: Fr = challenge_after(b"test", &absorptions, b"sumcheck_challenge");
+    let mut unversioned = versioned.clone();
+    unversioned.rands = vec![r];
+    unversioned.final_terms = vec![eval_mle(&[r], &a), eval_mle(&[r], &b)];
+
+    // Against one verifier only the versioned proof's openings line up
+    let opens = |proof: &SumcheckProof<Fr>| {
+        let (rands, eval) = proof.verify(&mut new_transcript(b"test")).unwrap();
+        rands == proof.rands && eval == proof.final_terms[0] * proof.final_terms[1]
+    };
+    assert!(opens(&versioned));
+    assert!(!opens(&unversioned));
 }
@@ -0,0 +1,41 @@
+use alloc::vec::Vec;
+use ark_ff::Field;
+
+// Montgomery's trick: inverts every element with one field inversion and
+// about 3n multiplications. Panics if any element is zero
+pub fn batch_inverse<F: Field>(elements: &[F]) -> Vec<F> {
+    // prefix[i] is the product of elements[..i]
+    let mut prefix = Vec::with_capacity(elements.len());
+    let mut acc = F::ONE;
+    for (i, x) in elements.iter().enumerate() {
+        assert!(!x.is_zero(), "batch_inverse: element {} is zero", i);
+        prefix.push(acc);
+        acc *= x;
+    }
+    let mut inv = acc.inverse().unwrap();
+    let mut inverses = prefix;
+    for (out, x) in inverses.iter_mut().zip(elements).rev() {
+        *out *= inv;
+        inv *= x;
+    }
+    inverses
+}
+
+#[test]
+fn batch_inverse_test() {
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+
+    let elements: Vec<Fr> = (1..=9).map(|i| Fr::from(i * i + 3)).collect();
+    let expected: Vec<Fr> = elements.iter().map(|x| x.inverse().unwrap()).collect();
+    assert_eq!(batch_inverse(&elements), expected);
+    assert!(batch_inverse::<Fr>(&[]).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "element 2 is zero")]
+fn batch_inverse_zero_test() {
+    use ark_curve25519::Fr;
+
+    batch_inverse(&[Fr::from(3), Fr::from(5), Fr::from(0), Fr::from(7)]);
+}
@@ -0,0 +1,458 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    multilinear::{chis, eval_mle, eval_mle_streaming, partial_eval, Opening},
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+pub struct LayerProof<F: PrimeField> {
+    sumcheck_proof: SumcheckProof<F>,
+    add_eval: F,
+    mul_eval: F,
+    left_eval: F,
+    right_eval: F,
+}
+
+// The layer summand add * (W(x) + W(y)) + mul * W(x) * W(y) is not a single
+// product, so its three terms are stacked under two selector bits s:
+//   s = 0: add * W(x) * 1
+//   s = 1: add * 1 * W(y)
+//   s = 2: mul * W(x) * W(y)
+//   s = 3: 0
+// Summing P * Q * R over (s, x, y) is then an ordinary degree-3 sumcheck
+fn stack<F: PrimeField>(
+    add: &[F],
+    mul: &[F],
+    w_left: &[F],
+    w_right: &[F],
+) -> (Vec<F>, Vec<F>, Vec<F>) {
+    let n = w_right.len();
+    let left: Vec<F> = (0..add.len()).map(|i| w_left[i / n]).collect();
+    let right: Vec<F> = (0..add.len()).map(|i| w_right[i % n]).collect();
+    let ones = vec![F::ONE; add.len()];
+    let zeros = vec![F::ZERO; add.len()];
+    let p = [add, add, mul, &zeros[..]].concat();
+    let q = [&left[..], &ones, &left, &zeros].concat();
+    let r = [&ones[..], &right, &right, &zeros].concat();
+    (p, q, r)
+}
+
+// Proves claim == sum_{x,y} add(x,y) * (W(x) + W(y)) + mul(x,y) * W(x) * W(y)
+// where add and mul are the wiring predicates with the output gate z already
+// bound, tabled over (x, y) with x in the high bits
+pub fn layer_sumcheck<F: PrimeField>(
+    add_mle: &[F],
+    mul_mle: &[F],
+    w_left: &[F],
+    w_right: &[F],
+    claim: F,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> LayerProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(add_mle.len(), mul_mle.len());
+    assert_eq!(add_mle.len(), w_left.len() * w_right.len());
+    let (p, q, r) = stack(add_mle, mul_mle, w_left, w_right);
+    let sumcheck_proof = SumcheckProof::prove(claim, vec![p, q, r], transcript);
+
+    let point = &sumcheck_proof.rands[2..];
+    let (rx, ry) = point.split_at(w_left.len().ilog2() as usize);
+    let add_eval = eval_mle(point, add_mle);
+    let mul_eval = eval_mle(point, mul_mle);
+    let left_eval = eval_mle(rx, w_left);
+    let right_eval = eval_mle(ry, w_right);
+    for eval in [add_eval, mul_eval, left_eval, right_eval] {
+        transcript.append_scalar(b"gkr_layer_eval", &eval);
+    }
+    let proof = LayerProof {
+        sumcheck_proof,
+        add_eval,
+        mul_eval,
+        left_eval,
+        right_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify([add_mle, mul_mle, w_left, w_right], claim, start);
+    proof
+}
+
+impl<F: PrimeField> LayerProof<F> {
+    // Replays `verify_layer_sumcheck` from the transcript state the prover
+    // started with and checks the openings it returns against the tables
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, tables: [&[F]; 4], claim: F, mut transcript: impl ProtocolTranscript<F>) {
+        let (left_len, right_len) = (tables[2].len(), tables[3].len());
+        crate::selfverify::check("gkr layer", || {
+            verify_layer_sumcheck(left_len, right_len, claim, self, &mut transcript).is_ok_and(
+                |openings| {
+                    openings
+                        .iter()
+                        .zip(tables)
+                        .all(|((point, eval), table)| eval_mle(point, table) == *eval)
+                },
+            )
+        });
+    }
+}
+
+// Returns the openings of add, mul (at (rx, ry)), W_left at rx, and W_right
+// at ry, which the caller must check
+pub fn verify_layer_sumcheck<F: PrimeField>(
+    left_len: usize,
+    right_len: usize,
+    claim: F,
+    proof: &LayerProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<[Opening<F>; 4], SumcheckError> {
+    if proof.sumcheck_proof.claim != claim {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    // Two selector bits, then x and y; P * Q * R has degree 3
+    let left_vars = left_len.ilog2() as usize;
+    let rounds = 2 + left_vars + right_len.ilog2() as usize;
+    let (r, expected) = proof.sumcheck_proof.verify_shape(rounds, 3, transcript)?;
+    let (rs, point) = r.split_at(2);
+    let s = chis(rs);
+    let (add, mul) = (proof.add_eval, proof.mul_eval);
+    let (left, right) = (proof.left_eval, proof.right_eval);
+    let p = (s[0] + s[1]) * add + s[2] * mul;
+    let q = (s[0] + s[2]) * left + s[1];
+    let r = s[0] + (s[1] + s[2]) * right;
+    if expected != p * q * r {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    for eval in [add, mul, left, right] {
+        transcript.append_scalar(b"gkr_layer_eval", &eval);
+    }
+    let (rx, ry) = point.split_at(left_vars);
+    Ok([
+        (point.to_vec(), add),
+        (point.to_vec(), mul),
+        (rx.to_vec(), left),
+        (ry.to_vec(), right),
+    ])
+}
+
+// One layer of a layered arithmetic circuit: the add and mul wiring
+// predicates tabled over (z, x, y), z the gate in this layer in the high
+// bits and x, y the gates of the layer below it. A grand product is the
+// circuit whose layers have only mul gates
+pub struct CircuitLayer<F: PrimeField> {
+    pub add: Vec<F>,
+    pub mul: Vec<F>,
+}
+
+pub struct CircuitProof<F: PrimeField> {
+    layer_proofs: Vec<LayerProof<F>>,
+}
+
+// Values of every layer, output layer first and the inputs last. Layers
+// are ordered from the output down to the one reading the inputs
+pub fn evaluate_circuit<F: PrimeField>(circuit: &[CircuitLayer<F>], inputs: &[F]) -> Vec<Vec<F>> {
+    let mut values = vec![inputs.to_vec()];
+    for layer in circuit.iter().rev() {
+        let below = values.last().unwrap();
+        let n = below.len();
+        let gates = layer.add.len() / (n * n);
+        let next = (0..gates)
+            .map(|z| {
+                (0..n * n)
+                    .map(|xy| {
+                        let (wx, wy) = (below[xy / n], below[xy % n]);
+                        let i = z * n * n + xy;
+                        layer.add[i] * (wx + wy) + layer.mul[i] * wx * wy
+                    })
+                    .sum()
+            })
+            .collect();
+        values.push(next);
+    }
+    values.reverse();
+    values
+}
+
+// The wiring table with its output gate bound to sum_i w_i * eq(z_i, .)
+fn bind_wiring<F: PrimeField>(table: &[F], bound: &[(F, Vec<F>)]) -> Vec<F> {
+    let mut out = vec![F::ZERO; table.len() >> bound[0].1.len()];
+    for (w, z) in bound {
+        for (o, v) in out.iter_mut().zip(partial_eval(table, z)) {
+            *o += *w * v;
+        }
+    }
+    out
+}
+
+// Reduces a claim about the outputs to claims about the inputs one layer at
+// a time with `layer_sumcheck`. The two claims W(rx), W(ry) each layer
+// leaves are folded into one with random alpha, beta, and the next layer's
+// wiring is bound at both points with the same weights
+pub fn prove_circuit<F: PrimeField>(
+    circuit: &[CircuitLayer<F>],
+    inputs: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> CircuitProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    let values = evaluate_circuit(circuit, inputs);
+    transcript.append_points(b"gkr_outputs", &values[0]);
+    let r = transcript.challenge_scalars(b"gkr_r", values[0].len().ilog2() as usize);
+    let mut claim = eval_mle(&r, &values[0]);
+    let mut bound = vec![(F::ONE, r)];
+    let mut layer_proofs = vec![];
+    for (layer, below) in circuit.iter().zip(&values[1..]) {
+        let add = bind_wiring(&layer.add, &bound);
+        let mul = bind_wiring(&layer.mul, &bound);
+        let proof = layer_sumcheck(&add, &mul, below, below, claim, transcript);
+        let point = &proof.sumcheck_proof.rands[2..];
+        let (rx, ry) = point.split_at(below.len().ilog2() as usize);
+        let alpha = transcript.challenge_scalar(b"gkr_alpha");
+        let beta = transcript.challenge_scalar(b"gkr_beta");
+        claim = alpha * proof.left_eval + beta * proof.right_eval;
+        bound = vec![(alpha, rx.to_vec()), (beta, ry.to_vec())];
+        layer_proofs.push(proof);
+    }
+    let proof = CircuitProof { layer_proofs };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(circuit, inputs, &values[0], start);
+    proof
+}
+
+impl<F: PrimeField> CircuitProof<F> {
+    // Replays `verify_circuit` from the transcript state the prover started
+    // with and checks the input openings it returns against the inputs
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(
+        &self,
+        circuit: &[CircuitLayer<F>],
+        inputs: &[F],
+        outputs: &[F],
+        mut transcript: impl ProtocolTranscript<F>,
+    ) {
+        crate::selfverify::check("gkr", || {
+            matches!(
+                verify_circuit(circuit, outputs, self, &mut transcript),
+                Ok(((rx, left), (ry, right)))
+                    if eval_mle(&rx, inputs) == left && eval_mle(&ry, inputs) == right
+            )
+        });
+    }
+}
+
+// Checks the proof against the public wiring and outputs, evaluating the
+// wiring MLEs directly. Returns the openings of the input MLE at rx and ry,
+// which the caller must check
+pub fn verify_circuit<F: PrimeField>(
+    circuit: &[CircuitLayer<F>],
+    outputs: &[F],
+    proof: &CircuitProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Opening<F>, Opening<F>), SumcheckError> {
+    assert!(!circuit.is_empty(), "gkr circuit has no layers");
+    assert!(
+        outputs.len().is_power_of_two(),
+        "gkr outputs must have power-of-two length, got {}",
+        outputs.len()
+    );
+    // One layer sumcheck per circuit layer
+    if proof.layer_proofs.len() != circuit.len() {
+        return Err(SumcheckError::RoundCountMismatch {
+            got: proof.layer_proofs.len(),
+            expected: circuit.len(),
+        });
+    }
+    transcript.append_points(b"gkr_outputs", outputs);
+    let mut gate_vars = outputs.len().ilog2() as usize;
+    let r = transcript.challenge_scalars(b"gkr_r", gate_vars);
+    let mut claim = eval_mle_streaming(&r, outputs);
+    let mut bound = vec![(F::ONE, r)];
+    let mut openings = None;
+    for (i, (layer, layer_proof)) in circuit.iter().zip(&proof.layer_proofs).enumerate() {
+        // The table has gate_vars + 2 * below_vars variables
+        let table_len = layer.add.len();
+        let table_vars = table_len.ilog2() as usize;
+        assert!(
+            table_len.is_power_of_two()
+                && layer.mul.len() == table_len
+                && table_vars >= gate_vars
+                && (table_vars - gate_vars).is_multiple_of(2),
+            "gkr layer {} wiring tables of length {} and {} do not fit {} gates",
+            i,
+            table_len,
+            layer.mul.len(),
+            1 << gate_vars
+        );
+        let below_vars = (table_vars - gate_vars) / 2;
+        let [(point, add_eval), (_, mul_eval), (rx, left), (ry, right)] = verify_layer_sumcheck(
+            1 << below_vars,
+            1 << below_vars,
+            claim,
+            layer_proof,
+            transcript,
+        )?;
+        let wiring = |table: &[F]| -> F {
+            bound
+                .iter()
+                .map(|(w, z)| *w * eval_mle_streaming(&[&z[..], &point[..]].concat(), table))
+                .sum()
+        };
+        if add_eval != wiring(&layer.add) || mul_eval != wiring(&layer.mul) {
+            return Err(SumcheckError::FinalEvalMismatch);
+        }
+        let alpha = transcript.challenge_scalar(b"gkr_alpha");
+        let beta = transcript.challenge_scalar(b"gkr_beta");
+        claim = alpha * left + beta * right;
+        bound = vec![(alpha, rx.clone()), (beta, ry.clone())];
+        gate_vars = below_vars;
+        openings = Some(((rx, left), (ry, right)));
+    }
+    Ok(openings.unwrap())
+}
+
+#[test]
+fn test_layer_sumcheck() {
+    use crate::multilinear::set_variable;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    // Two gates over inputs W = [3, 5]: gate 0 = W0 + W1, gate 1 = W0 * W1.
+    // Wiring tables are indexed by (z, x, y)
+    let w = vec![Fr::from(3), Fr::from(5)];
+    let mut add = vec![Fr::from(0); 8];
+    let mut mul = vec![Fr::from(0); 8];
+    add[0b001] = Fr::from(1);
+    mul[0b101] = Fr::from(1);
+    let output = vec![Fr::from(8), Fr::from(15)];
+
+    let z = Fr::from(7);
+    let claim = eval_mle(&[z], &output);
+    let add_z = set_variable(&add, z);
+    let mul_z = set_variable(&mul, z);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = layer_sumcheck(&add_z, &mul_z, &w, &w, claim, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let [(pa, add_eval), (pm, mul_eval), (rx, left), (ry, right)] =
+        verify_layer_sumcheck(w.len(), w.len(), claim, &proof, &mut vtranscript).unwrap();
+    assert_eq!(add_eval, eval_mle(&pa, &add_z));
+    assert_eq!(mul_eval, eval_mle(&pm, &mul_z));
+    assert_eq!(left, eval_mle(&rx, &w));
+    assert_eq!(right, eval_mle(&ry, &w));
+}
+
+#[cfg(test)]
+fn wiring(gates: usize, below: usize, wires: &[(usize, usize, usize)]) -> Vec<ark_curve25519::Fr> {
+    use ark_ff::Field;
+    let mut table = vec![ark_curve25519::Fr::ZERO; gates * below * below];
+    for &(z, x, y) in wires {
+        table[z * below * below + x * below + y] = ark_curve25519::Fr::ONE;
+    }
+    table
+}
+
+#[test]
+fn test_circuit() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let inputs: Vec<Fr> = [1u64, 2, 3, 4].map(Fr::from).to_vec();
+    // g0 = in0 * in1, g1 = in2 + in3; then o0 = g0 + g1, o1 = g0 * g1
+    let circuit = vec![
+        CircuitLayer {
+            add: wiring(2, 2, &[(0, 0, 1)]),
+            mul: wiring(2, 2, &[(1, 0, 1)]),
+        },
+        CircuitLayer {
+            add: wiring(2, 4, &[(1, 2, 3)]),
+            mul: wiring(2, 4, &[(0, 0, 1)]),
+        },
+    ];
+    let outputs = evaluate_circuit(&circuit, &inputs)[0].clone();
+    assert_eq!(outputs, vec![Fr::from(9u64), Fr::from(14u64)]);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_circuit(&circuit, &inputs, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((rx, left), (ry, right)) =
+        verify_circuit(&circuit, &outputs, &proof, &mut vtranscript).unwrap();
+    assert_eq!(left, eval_mle(&rx, &inputs));
+    assert_eq!(right, eval_mle(&ry, &inputs));
+}
+
+#[test]
+fn test_circuit_wrong_outputs() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let inputs: Vec<Fr> = [1u64, 2, 3, 4].map(Fr::from).to_vec();
+    let circuit = vec![CircuitLayer {
+        add: wiring(2, 4, &[(1, 2, 3)]),
+        mul: wiring(2, 4, &[(0, 0, 1)]),
+    }];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_circuit(&circuit, &inputs, &mut transcript);
+    let outputs = vec![Fr::from(2u64), Fr::from(8u64)];
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_circuit(&circuit, &outputs, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::UnexpectedClaim)
+    );
+
+    // A layer sumcheck cut short is rejected rather than split out of range
+    let mut truncated = prove_circuit(&circuit, &inputs, &mut Transcript::new(b"test_transcript"));
+    let sumcheck_proof = &mut truncated.layer_proofs[0].sumcheck_proof;
+    sumcheck_proof.polynomials.truncate(1);
+    sumcheck_proof.rounds = 1;
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_circuit(
+            &circuit,
+            &[Fr::from(2u64), Fr::from(7u64)],
+            &truncated,
+            &mut vtranscript
+        )
+        .err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 1,
+            expected: 6
+        })
+    );
+}
+
+#[test]
+fn test_grand_product_circuit() {
+    use crate::grandproduct::GrandProductProof;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    // A grand product is a binary tree of mul gates with no adds
+    let inputs: Vec<Fr> = [3u64, 5, 7, 2].map(Fr::from).to_vec();
+    let circuit = vec![
+        CircuitLayer {
+            add: wiring(1, 2, &[]),
+            mul: wiring(1, 2, &[(0, 0, 1)]),
+        },
+        CircuitLayer {
+            add: wiring(2, 4, &[]),
+            mul: wiring(2, 4, &[(0, 0, 1), (1, 2, 3)]),
+        },
+    ];
+    let product: Fr = inputs.iter().product();
+    let outputs = evaluate_circuit(&circuit, &inputs)[0].clone();
+    assert_eq!(outputs, vec![product]);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_circuit(&circuit, &inputs, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((rx, left), (ry, right)) =
+        verify_circuit(&circuit, &outputs, &proof, &mut vtranscript).unwrap();
+    assert_eq!(left, eval_mle(&rx, &inputs));
+    assert_eq!(right, eval_mle(&ry, &inputs));
+
+    // The dedicated grand product proves the same product
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (grand_product, _) = GrandProductProof::prove(&inputs, product, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(grand_product.verify(product, &mut vtranscript).is_ok());
+}
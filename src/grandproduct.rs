@@ -1,115 +1,849 @@
-use ark_ff::PrimeField;
+use alloc::borrow::Cow;
+use alloc::{vec, vec::Vec};
+
+use ark_ff::{Field, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 use crate::{
-    fiatshamir::ProtocolTranscript,
+    fiatshamir::{ProtocolTranscript, Provable},
     multilinear::{chis, eval_eq, eval_mle},
-    sumcheck::{self, SumcheckProof},
-    univariate::eval_ule,
+    sumcheck::{absorb_ext, ext_challenge, lift, ExtensionSumcheckProof, SumcheckProof},
 };
 
-fn compute_tree<F: PrimeField + From<i32>>(witness: &[F]) -> Vec<Vec<F>> {
-    // TODO: Is this the best data structure? if so, optimize
-    let num_layers = witness.len().ilog2() as usize;
-    let mut last = witness.to_vec();
-    let mut layers = vec![last.clone()];
-    for _ in 0..(num_layers - 1) {
-        let mut next = vec![];
-        let half = last.len() / 2;
-        for i in 0..half {
-            next.push(last[i * 2] * last[i * 2 + 1])
-        }
-        layers.push(next.clone());
-        last = next;
+// Pads a witness with ones up to the next power of two, which leaves its
+// product unchanged. Proofs over a shorter witness are proofs over this
+// padded one, so its final claim is an evaluation of the padded MLE
+pub fn pad_witness<F: PrimeField>(witness: &[F]) -> Vec<F> {
+    let mut padded = witness.to_vec();
+    padded.resize(witness.len().next_power_of_two(), F::ONE);
+    padded
+}
+
+fn compute_tree<F: PrimeField>(witness: &[F]) -> Vec<Vec<F>> {
+    // TODO: Is this the best data structure?
+    let mut layers = vec![pad_witness(witness)];
+    while layers.last().unwrap().len() > 2 {
+        let next = layers
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| pair[0] * pair[1])
+            .collect();
+        layers.push(next);
     }
+    // Only swaps the layer handles, not their contents
     layers.reverse();
     layers
 }
 
-fn factor<F: PrimeField>(witness: &[F]) -> (Vec<F>, Vec<F>) {
-    let half = witness.len() / 2;
-    let (mut l, mut r) = (vec![], vec![]);
-    for i in 0..half {
-        l.push(witness[i * 2]);
-        r.push(witness[i * 2 + 1]);
+// Recomputes the layer with 2^(depth + 1) entries from the leaves, holding
+// at most one intermediate layer at a time
+fn compute_layer<F: PrimeField>(witness: &[F], depth: usize) -> Cow<'_, [F]> {
+    let mut layer = Cow::Borrowed(witness);
+    while layer.len() > 2 << depth {
+        layer = Cow::Owned(layer.chunks(2).map(|pair| pair[0] * pair[1]).collect());
     }
-    (l, r)
+    layer
+}
+
+// Splits a layer into `arity` interleaved tables so that the i-th entry of
+// table c is witness[i * arity + c]
+fn factor<F: PrimeField>(witness: &[F], arity: usize) -> Vec<Vec<F>> {
+    (0..arity)
+        .map(|c| witness.iter().skip(c).step_by(arity).cloned().collect())
+        .collect()
+}
+
+// A verified layer claim and the point it holds at
+pub type LayerClaim<F> = (F, Vec<F>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrandProductError {
+    // The proof is for a different product than the verifier expects
+    ClaimMismatch,
+    MalformedProof,
+    RootMismatch,
+    LayerClaimMismatch { layer: usize },
+    LayerRelationMismatch { layer: usize },
+    // The bytes are not a canonical encoding of a proof
+    Undecodable,
 }
 
-pub struct GrandProductProof<F: PrimeField + From<i32>> {
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct GrandProductProof<F: PrimeField> {
     claims: Vec<F>,
-    left_evals: Vec<F>,
-    right_evals: Vec<F>,
+    // Evaluations of the children of each reduced layer: two for a single
+    // layer step, four when two layers were merged into one sumcheck
+    evals: Vec<Vec<F>>,
     sumcheck_proofs: Vec<SumcheckProof<F>>,
 }
 
-impl<F: PrimeField + From<i32>> GrandProductProof<F> {
-    pub fn prove(witness: &[F], mut claim: F, transcript: &mut impl ProtocolTranscript<F>) -> Self {
-        let layers = compute_tree(witness);
+// The leaves values[i]^exponents[i] of a weighted grand product
+pub fn weighted_leaves<F: PrimeField>(values: &[F], exponents: &[u32]) -> Vec<F> {
+    values
+        .iter()
+        .zip(exponents)
+        .map(|(v, &e)| v.pow([e as u64]))
+        .collect()
+}
+
+impl<F: PrimeField> GrandProductProof<F> {
+    // Returns the proof and the point z at which the witness MLE must equal
+    // the final claim. z has log2(witness.len()) coordinates in the same
+    // order as `chis`/`eval_mle`: z[0] binds the most significant bit of the
+    // witness index and the last coordinate is the final layer's challenge,
+    // which picks between the left (even) and right (odd) leaf. The verifier
+    // gets the same z from `verify`; it is not part of the proof
+    pub fn prove(
+        witness: &[F],
+        claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<F>) {
+        Self::prove_layers(witness, claim, false, false, transcript)
+    }
+
+    // Produces the same proof as `prove` without materializing the tree.
+    // Each layer is rebuilt from the leaves when its sumcheck runs, so peak
+    // memory is the witness plus one layer (about n rather than 2n) at the
+    // cost of O(n log n) rather than O(n) multiplications
+    pub fn prove_streaming(
+        witness: &[F],
+        claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<F>) {
+        Self::prove_layers(witness, claim, false, true, transcript)
+    }
+
+    // Proves two adjacent layers per sumcheck (eq times a four-way product),
+    // halving the number of sumchecks at the cost of higher degree rounds
+    pub fn prove_merged(
+        witness: &[F],
+        claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<F>) {
+        Self::prove_layers(witness, claim, true, false, transcript)
+    }
+
+    // Proves claim = prod values[i]^exponents[i] by raising each leaf to its
+    // exponent before building the tree. Square-and-multiply costs under
+    // 2 * log2(e) multiplications per leaf and the tree keeps n leaves,
+    // where replicating each value e times would grow it to sum(e) leaves.
+    // The final claim is about the MLE of the powered leaves, so that is
+    // the table the caller must open
+    pub fn prove_weighted(
+        values: &[F],
+        exponents: &[u32],
+        claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<F>) {
+        assert_eq!(values.len(), exponents.len());
+        let powered = weighted_leaves(values, exponents);
+        Self::prove(&powered, claim, transcript)
+    }
+
+    fn prove_layers(
+        witness: &[F],
+        mut claim: F,
+        merge: bool,
+        stream: bool,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<F>) {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let padded;
+        let witness = if witness.len().is_power_of_two() {
+            witness
+        } else {
+            padded = pad_witness(witness);
+            &padded[..]
+        };
+        let num_layers = witness.len().ilog2() as usize;
+        let tree = if stream {
+            vec![]
+        } else {
+            compute_tree(witness)
+        };
+        let layer = |depth: usize| {
+            if stream {
+                compute_layer(witness, depth)
+            } else {
+                Cow::Borrowed(&tree[depth][..])
+            }
+        };
+        transcript.append_version();
         transcript.append_scalar(b"grand_product_claim", &claim);
-        let mut left_evals = vec![];
-        let mut right_evals = vec![];
+        let mut evals = vec![];
         let mut claims = vec![claim];
         let mut sumcheck_proofs = vec![];
         let mut z = vec![];
-        let mut rands = vec![];
 
+        let top = layer(0).into_owned();
+        // The root's children are fixed before the challenge that folds them
+        transcript.append_points(b"grand_product_top", &top);
         let challenge = transcript.challenge_scalar(b"grand_product_challenge");
-        rands.push(challenge);
-        claim = eval_ule(&[layers[0][0], layers[0][1]], challenge);
+        claim = eval_mle(&[challenge], &top);
         claims.push(claim);
-        left_evals.push(layers[0][0]);
-        right_evals.push(layers[0][1]);
+        evals.push(top);
         z.push(challenge);
 
-        for i in 1..layers.len() {
-            let layer = &layers[i];
+        let mut i = 1;
+        while i < num_layers {
+            let step = if merge && i + 1 < num_layers { 2 } else { 1 };
             let eq: Vec<F> = chis(&z);
-            let (l, r) = factor(layer);
-            let sumcheck_proof =
-                SumcheckProof::prove(claim, vec![eq.clone(), l.clone(), r.clone()], transcript);
-            rands = sumcheck_proof.rands.clone();
-            sumcheck_proofs.push(sumcheck_proof.clone());
-            left_evals.push(sumcheck_proof.final_terms[1].clone());
-            right_evals.push(sumcheck_proof.final_terms[2].clone());
-            transcript.append_scalar(b"grand_product_point", &sumcheck_proof.final_terms[1]);
-            transcript.append_scalar(b"grand_product_point", &sumcheck_proof.final_terms[2]);
-            let challenge = transcript.challenge_scalar(b"grand_product_challenge");
-            rands.push(challenge);
-            claim = eval_ule(
-                &[sumcheck_proof.final_terms[1], sumcheck_proof.final_terms[2]],
-                challenge,
-            );
+            let mles = core::iter::once(eq)
+                .chain(factor(&layer(i + step - 1), 1 << step))
+                .collect();
+            let sumcheck_proof = SumcheckProof::prove(claim, mles, transcript);
+            let children = sumcheck_proof.final_terms[1..].to_vec();
+            for child in children.iter() {
+                transcript.append_scalar(b"grand_product_point", child);
+            }
+            let challenges = transcript.challenge_scalars(b"grand_product_challenge", step);
+            claim = eval_mle(&challenges, &children);
             claims.push(claim);
-            z = rands;
+            evals.push(children);
+            z = sumcheck_proof.rands.clone();
+            z.extend(challenges);
+            sumcheck_proofs.push(sumcheck_proof);
+            i += step;
         }
-        Self {
+        let proof = Self {
             claims,
-            left_evals,
-            right_evals,
+            evals,
             sumcheck_proofs,
-        }
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(&z, start);
+        (proof, z)
+    }
+
+    // Replays the verifier from the transcript state the prover started with
+    // and panics if it disagrees with what the prover produced
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, point: &[F], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check(
+            "grand product",
+            || matches!(self.verify(self.claims[0], &mut transcript), Ok((_, z)) if z == point),
+        );
+    }
+
+    pub fn claim(&self) -> F {
+        self.claims[0]
+    }
+
+    // Returns (final_claim, z) with z ordered as in `prove`. The caller
+    // must check eval_mle(z, witness) == final_claim against its commitment.
+    // The proof must be for expected_product, so a prover cannot substitute
+    // a product of its choosing
+    pub fn verify(
+        &self,
+        expected_product: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(F, Vec<F>), GrandProductError> {
+        let (claim, mut layers) = self.verify_verbose(expected_product, transcript)?;
+        Ok((claim, layers.pop().unwrap().1))
+    }
+
+    // Decodes a compressed proof and verifies it as `verify` does, for a
+    // standalone verifier that only ever sees the bytes
+    pub fn verify_from_bytes(
+        bytes: &[u8],
+        expected_product: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(F, Vec<F>), GrandProductError> {
+        let proof =
+            Self::deserialize_compressed(bytes).map_err(|_| GrandProductError::Undecodable)?;
+        proof.verify(expected_product, transcript)
+    }
+
+    // `verify`, panicking on a bad proof
+    pub fn verify_unchecked(
+        &self,
+        expected_product: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (F, Vec<F>) {
+        self.verify(expected_product, transcript)
+            .expect("grand product verification failed")
     }
 
-    pub fn verify(&self, transcript: &mut impl ProtocolTranscript<F>) -> (F, Vec<F>) {
+    // Verifies as `verify` does, additionally returning the (claim, point)
+    // reached at every layer from the root product (at the empty point) down
+    // to the leaves, so intermediate products can be audited. Merged steps
+    // skip a layer
+    pub fn verify_verbose(
+        &self,
+        expected_product: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(F, Vec<LayerClaim<F>>), GrandProductError> {
+        if self.claims.is_empty() {
+            return Err(GrandProductError::MalformedProof);
+        }
+        if self.claims[0] != expected_product {
+            return Err(GrandProductError::ClaimMismatch);
+        }
+        transcript.append_version();
         transcript.append_scalar(b"grand_product_claim", &self.claims[0]);
-        assert_eq!(self.left_evals.len(), self.right_evals.len());
-        assert_eq!(self.left_evals.len(), self.claims.len() - 1);
-        let mut z = vec![];
-        assert_eq!(self.claims[0], self.left_evals[0] * self.right_evals[0]);
+        if self.evals.len() != self.claims.len() - 1
+            || self.evals.len() != self.sumcheck_proofs.len() + 1
+            || self.evals[0].len() != 2
+        {
+            return Err(GrandProductError::MalformedProof);
+        }
+        let mut layers = vec![(self.claims[0], vec![])];
+        if self.claims[0] != self.evals[0][0] * self.evals[0][1] {
+            return Err(GrandProductError::RootMismatch);
+        }
+        transcript.append_points(b"grand_product_top", &self.evals[0]);
         let challenge = transcript.challenge_scalar(b"grand_product_challenge");
-        z.push(challenge);
+        let mut claim = eval_mle(&[challenge], &self.evals[0]);
+        let mut z = vec![challenge];
+        layers.push((claim, z.clone()));
 
-        for i in 1..self.claims.len() - 1 {
-            let (rands, expected) = self.sumcheck_proofs[i - 1].verify(transcript);
-            transcript.append_scalar(b"grand_product_point", &self.left_evals[i]);
-            transcript.append_scalar(b"grand_product_point", &self.right_evals[i]);
+        for i in 1..self.evals.len() {
+            let children = &self.evals[i];
+            // Each layer's sumcheck runs over the variables fixed so far
+            if (children.len() != 2 && children.len() != 4)
+                || self.sumcheck_proofs[i - 1].rounds != z.len()
+            {
+                return Err(GrandProductError::MalformedProof);
+            }
+            let (rands, expected) = self.sumcheck_proofs[i - 1]
+                .verify_claim(claim, transcript)
+                .map_err(|_| GrandProductError::LayerClaimMismatch { layer: i })?;
+            for child in children.iter() {
+                transcript.append_scalar(b"grand_product_point", child);
+            }
+            let step = children.len().ilog2() as usize;
+            let challenges = transcript.challenge_scalars(b"grand_product_challenge", step);
+            let eq = eval_eq(&z, &rands);
+            if expected != eq * children.iter().product::<F>() {
+                return Err(GrandProductError::LayerRelationMismatch { layer: i });
+            }
+            claim = eval_mle(&challenges, children);
+            z = rands;
+            z.extend(challenges);
+            layers.push((claim, z.clone()));
+        }
+        Ok((claim, layers))
+    }
+}
+
+// Statement: the claimed product. Witness: the leaves
+impl<F: PrimeField> Provable<F> for GrandProductProof<F> {
+    type Statement = F;
+    type Witness = Vec<F>;
+    type Proof = Self;
+    type Output = (F, Vec<F>);
+
+    fn prove(claim: &F, witness: Vec<F>, transcript: &mut impl ProtocolTranscript<F>) -> Self {
+        GrandProductProof::prove(&witness, *claim, transcript).0
+    }
+
+    fn verify(
+        claim: &F,
+        proof: &Self,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Option<(F, Vec<F>)> {
+        proof.verify(*claim, transcript).ok()
+    }
+}
+
+// Several grand products proved layer by layer in lockstep, with each
+// layer's sumchecks batched into one. Trees are aligned at the root rather
+// than the leaves: layer k of every tree has 2^k entries whatever its
+// depth, so the sumchecks reducing layer k to k + 1 all run over k
+// variables and share one batch and one set of challenges. A witness
+// simply leaves the batch once its leaves are reached, and ends at the
+// point reached at that layer, so witnesses of equal length share a point
+#[derive(Clone)]
+pub struct BatchedGrandProductProof<F: PrimeField> {
+    claims: Vec<F>,
+    // log2 of each witness's padded length
+    depths: Vec<usize>,
+    // The two top-layer entries of each witness's tree
+    tops: Vec<Vec<F>>,
+    // One per layer below the top of the deepest tree, batching the
+    // witnesses still above their leaves
+    sumcheck_proofs: Vec<SumcheckProof<F>>,
+}
+
+impl<F: PrimeField> GrandProductProof<F> {
+    // Returns the proof and, per witness, the point its MLE must be opened
+    // at. Witnesses may have different lengths, each at least two
+    pub fn prove_batched(
+        witnesses: &[Vec<F>],
+        claims: &[F],
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (BatchedGrandProductProof<F>, Vec<Vec<F>>) {
+        assert_eq!(witnesses.len(), claims.len());
+        assert!(
+            witnesses.iter().all(|w| w.len() >= 2),
+            "batched grand product witnesses need at least two entries"
+        );
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let depths: Vec<usize> = witnesses
+            .iter()
+            .map(|w| w.len().next_power_of_two().ilog2() as usize)
+            .collect();
+        let num_layers = *depths.iter().max().unwrap();
+        let trees: Vec<Vec<Vec<F>>> = witnesses.iter().map(|w| compute_tree(w)).collect();
+        transcript.append_version();
+        for (claim, &depth) in claims.iter().zip(&depths) {
+            transcript.append_scalar(b"grand_product_claim", claim);
+            transcript.append_usize(b"grand_product_depth", depth);
+        }
+        let tops: Vec<Vec<F>> = trees.iter().map(|tree| tree[0].clone()).collect();
+        // Every root's children are fixed before the challenge that folds them
+        for top in &tops {
+            transcript.append_points(b"grand_product_top", top);
+        }
+        let challenge = transcript.challenge_scalar(b"grand_product_challenge");
+        let mut layer_claims: Vec<F> = tops.iter().map(|top| eval_mle(&[challenge], top)).collect();
+        let mut z = vec![challenge];
+        let mut points = vec![vec![]; witnesses.len()];
+        finish_layer(&depths, 1, &z, &mut points);
+
+        let mut sumcheck_proofs = vec![];
+        for i in 1..num_layers {
+            let active: Vec<usize> = (0..trees.len()).filter(|&j| depths[j] > i).collect();
+            let eq = chis(&z);
+            let instances = active
+                .iter()
+                .map(|&j| {
+                    core::iter::once(eq.clone())
+                        .chain(factor(&trees[j][i], 2))
+                        .collect()
+                })
+                .collect();
+            let active_claims: Vec<F> = active.iter().map(|&j| layer_claims[j]).collect();
+            let sumcheck_proof =
+                SumcheckProof::prove_batched(&active_claims, instances, transcript);
+            let children: Vec<&[F]> = sumcheck_proof
+                .final_terms
+                .chunks(3)
+                .map(|t| &t[1..])
+                .collect();
+            for child in children.iter().flat_map(|c| c.iter()) {
+                transcript.append_scalar(b"grand_product_point", child);
+            }
             let challenge = transcript.challenge_scalar(b"grand_product_challenge");
+            for (&j, child) in active.iter().zip(&children) {
+                layer_claims[j] = eval_mle(&[challenge], child);
+            }
+            z = sumcheck_proof.rands.clone();
+            z.push(challenge);
+            finish_layer(&depths, i + 1, &z, &mut points);
+            sumcheck_proofs.push(sumcheck_proof);
+        }
+        let proof = BatchedGrandProductProof {
+            claims: claims.to_vec(),
+            depths,
+            tops,
+            sumcheck_proofs,
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(&points, start);
+        (proof, points)
+    }
+}
+
+// Records z as the final point of every witness whose leaves are layer
+// `depth`
+fn finish_layer<F: PrimeField>(depths: &[usize], depth: usize, z: &[F], points: &mut [Vec<F>]) {
+    for (point, _) in points.iter_mut().zip(depths).filter(|(_, &d)| d == depth) {
+        *point = z.to_vec();
+    }
+}
+
+impl<F: PrimeField> BatchedGrandProductProof<F> {
+    pub fn claims(&self) -> &[F] {
+        &self.claims
+    }
+
+    // Replays `verify_batched` from the transcript state the prover started
+    // with and checks it reaches the points the prover returned
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, points: &[Vec<F>], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("batched grand product", || {
+            matches!(
+                self.verify_batched(&self.claims, &mut transcript),
+                Ok(results) if results.iter().map(|(_, z)| z).eq(points)
+            )
+        });
+    }
+
+    // Returns a (final_claim, z) pair per witness, in order. As with
+    // `GrandProductProof::verify`, the caller checks each against its
+    // commitment, whose length must match z's
+    pub fn verify_batched(
+        &self,
+        expected_products: &[F],
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<Vec<LayerClaim<F>>, GrandProductError> {
+        if self.claims != expected_products {
+            return Err(GrandProductError::ClaimMismatch);
+        }
+        let num_layers = self.depths.iter().max().cloned().unwrap_or(0);
+        if self.tops.len() != self.claims.len()
+            || self.depths.len() != self.claims.len()
+            || self.tops.iter().any(|top| top.len() != 2)
+            || self.depths.contains(&0)
+            || self.sumcheck_proofs.len() + 1 != num_layers
+        {
+            return Err(GrandProductError::MalformedProof);
+        }
+        transcript.append_version();
+        for ((claim, top), &depth) in self.claims.iter().zip(&self.tops).zip(&self.depths) {
+            transcript.append_scalar(b"grand_product_claim", claim);
+            transcript.append_usize(b"grand_product_depth", depth);
+            if *claim != top[0] * top[1] {
+                return Err(GrandProductError::RootMismatch);
+            }
+        }
+        for top in &self.tops {
+            transcript.append_points(b"grand_product_top", top);
+        }
+        let challenge = transcript.challenge_scalar(b"grand_product_challenge");
+        let mut layer_claims: Vec<F> = self
+            .tops
+            .iter()
+            .map(|top| eval_mle(&[challenge], top))
+            .collect();
+        let mut z = vec![challenge];
+        let mut points = vec![vec![]; self.claims.len()];
+        finish_layer(&self.depths, 1, &z, &mut points);
+
+        for (i, sumcheck_proof) in self.sumcheck_proofs.iter().enumerate() {
+            let layer = i + 1;
+            if sumcheck_proof.rounds != z.len() {
+                return Err(GrandProductError::MalformedProof);
+            }
+            let active: Vec<usize> = (0..self.claims.len())
+                .filter(|&j| self.depths[j] > layer)
+                .collect();
+            let active_claims: Vec<F> = active.iter().map(|&j| layer_claims[j]).collect();
+            let sizes = vec![3; active.len()];
+            let (rands, openings) = sumcheck_proof
+                .verify_batched(&active_claims, &sizes, transcript)
+                .map_err(|_| GrandProductError::LayerClaimMismatch { layer })?;
             let eq = eval_eq(&z, &rands);
-            assert_eq!(expected, eq * self.left_evals[i] * self.right_evals[i]);
+            if openings.iter().any(|terms| terms[0] != eq) {
+                return Err(GrandProductError::LayerRelationMismatch { layer });
+            }
+            for child in openings.iter().flat_map(|terms| &terms[1..]) {
+                transcript.append_scalar(b"grand_product_point", child);
+            }
+            let challenge = transcript.challenge_scalar(b"grand_product_challenge");
+            for (&j, terms) in active.iter().zip(&openings) {
+                layer_claims[j] = eval_mle(&[challenge], &terms[1..]);
+            }
+            z = rands;
+            z.push(challenge);
+            finish_layer(&self.depths, layer + 1, &z, &mut points);
+        }
+        Ok(layer_claims.into_iter().zip(points).collect())
+    }
+}
+
+// Layers of the binary tree that adds fractions pairwise without
+// inverting, (a, b) + (c, d) -> (a * d + c * b, b * d), smallest first
+fn compute_fraction_tree<F: PrimeField>(
+    numerators: &[F],
+    denominators: &[F],
+) -> Vec<(Vec<F>, Vec<F>)> {
+    let mut layers = vec![(numerators.to_vec(), denominators.to_vec())];
+    while layers.last().unwrap().0.len() > 2 {
+        let (p, q) = layers.last().unwrap();
+        let next = (0..p.len() / 2)
+            .map(|i| {
+                (
+                    p[2 * i] * q[2 * i + 1] + p[2 * i + 1] * q[2 * i],
+                    q[2 * i] * q[2 * i + 1],
+                )
+            })
+            .unzip();
+        layers.push(next);
+    }
+    layers.reverse();
+    layers
+}
+
+// Proves sum_i numerators[i] / denominators[i] = num / den for a claimed
+// (num, den) pair, as LogUp-style lookups need, with no inversions. Each
+// layer's numerator and denominator claims are folded into one sumcheck
+// with a random lambda
+#[derive(Clone)]
+pub struct FractionalGrandProductProof<F: PrimeField> {
+    claim: (F, F),
+    // The top layer's two numerators then its two denominators
+    top: Vec<F>,
+    sumcheck_proofs: Vec<SumcheckProof<F>>,
+}
+
+impl<F: PrimeField> FractionalGrandProductProof<F> {
+    pub fn prove(
+        numerators: &[F],
+        denominators: &[F],
+        claim: (F, F),
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        assert_eq!(numerators.len(), denominators.len());
+        assert!(numerators.len().is_power_of_two() && numerators.len() >= 2);
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let tree = compute_fraction_tree(numerators, denominators);
+        transcript.append_version();
+        transcript.append_scalar(b"fraction_claim_num", &claim.0);
+        transcript.append_scalar(b"fraction_claim_den", &claim.1);
+        let top: Vec<F> = tree[0].0.iter().chain(&tree[0].1).cloned().collect();
+        // The root's fractions are fixed before the challenge that folds them
+        transcript.append_points(b"fraction_top", &top);
+        let challenge = transcript.challenge_scalar(b"fraction_challenge");
+        let mut p_claim = eval_mle(&[challenge], &top[..2]);
+        let mut q_claim = eval_mle(&[challenge], &top[2..]);
+        let mut z = vec![challenge];
+
+        let mut sumcheck_proofs = vec![];
+        for (p, q) in &tree[1..] {
+            let lambda = transcript.challenge_scalar(b"fraction_lambda");
+            let mles = core::iter::once(chis(&z))
+                .chain(factor(p, 2))
+                .chain(factor(q, 2))
+                .collect();
+            let sumcheck_proof = SumcheckProof::prove_with(
+                p_claim + lambda * q_claim,
+                mles,
+                3,
+                |v: &[F]| v[0] * (v[1] * v[4] + v[2] * v[3] + lambda * v[3] * v[4]),
+                transcript,
+            );
+            let children = &sumcheck_proof.final_terms[1..];
+            for child in children {
+                transcript.append_scalar(b"fraction_point", child);
+            }
+            let challenge = transcript.challenge_scalar(b"fraction_challenge");
+            p_claim = eval_mle(&[challenge], &children[..2]);
+            q_claim = eval_mle(&[challenge], &children[2..]);
+            z = sumcheck_proof.rands.clone();
+            z.push(challenge);
+            sumcheck_proofs.push(sumcheck_proof);
+        }
+        let proof = FractionalGrandProductProof {
+            claim,
+            top,
+            sumcheck_proofs,
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(numerators, denominators, start);
+        proof
+    }
+
+    // Replays `verify` from the transcript state the prover started with and
+    // checks the claims it returns against the two witnesses
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(
+        &self,
+        numerators: &[F],
+        denominators: &[F],
+        mut transcript: impl ProtocolTranscript<F>,
+    ) {
+        crate::selfverify::check("fractional grand product", || {
+            matches!(
+                self.verify(self.claim, &mut transcript),
+                Ok((p, q, z)) if p == eval_mle(&z, numerators) && q == eval_mle(&z, denominators)
+            )
+        });
+    }
+
+    // Returns the numerator and denominator claims and the point z they hold
+    // at, which the caller must check against its commitments to the two
+    // witnesses
+    pub fn verify(
+        &self,
+        expected: (F, F),
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(F, F, Vec<F>), GrandProductError> {
+        if self.claim != expected {
+            return Err(GrandProductError::ClaimMismatch);
+        }
+        if self.top.len() != 4 {
+            return Err(GrandProductError::MalformedProof);
+        }
+        let (num, den) = self.claim;
+        if num != self.top[0] * self.top[3] + self.top[1] * self.top[2]
+            || den != self.top[2] * self.top[3]
+        {
+            return Err(GrandProductError::RootMismatch);
+        }
+        transcript.append_version();
+        transcript.append_scalar(b"fraction_claim_num", &num);
+        transcript.append_scalar(b"fraction_claim_den", &den);
+        transcript.append_points(b"fraction_top", &self.top);
+        let challenge = transcript.challenge_scalar(b"fraction_challenge");
+        let mut p_claim = eval_mle(&[challenge], &self.top[..2]);
+        let mut q_claim = eval_mle(&[challenge], &self.top[2..]);
+        let mut z = vec![challenge];
+
+        for (i, sumcheck_proof) in self.sumcheck_proofs.iter().enumerate() {
+            let layer = i + 1;
+            if sumcheck_proof.rounds != z.len() {
+                return Err(GrandProductError::MalformedProof);
+            }
+            let lambda = transcript.challenge_scalar(b"fraction_lambda");
+            let (rands, expected) = sumcheck_proof
+                .verify_claim(p_claim + lambda * q_claim, transcript)
+                .map_err(|_| GrandProductError::LayerClaimMismatch { layer })?;
+            let v = &sumcheck_proof.final_terms;
+            if v.len() != 5 {
+                return Err(GrandProductError::MalformedProof);
+            }
+            if expected != eval_eq(&z, &rands) * (v[1] * v[4] + v[2] * v[3] + lambda * v[3] * v[4])
+            {
+                return Err(GrandProductError::LayerRelationMismatch { layer });
+            }
+            for child in &v[1..] {
+                transcript.append_scalar(b"fraction_point", child);
+            }
+            let challenge = transcript.challenge_scalar(b"fraction_challenge");
+            p_claim = eval_mle(&[challenge], &v[1..3]);
+            q_claim = eval_mle(&[challenge], &v[3..]);
+            z = rands;
+            z.push(challenge);
+        }
+        Ok((p_claim, q_claim, z))
+    }
+
+    // `verify`, panicking on a bad proof
+    pub fn verify_unchecked(
+        &self,
+        expected: (F, F),
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (F, F, Vec<F>) {
+        self.verify(expected, transcript)
+            .expect("fractional grand product verification failed")
+    }
+}
+
+// A grand product whose leaves stay in F while the layer challenges, and
+// so the points the layer claims hold at, live in the extension E. Each
+// layer is an ExtensionSumcheckProof, so its soundness error drops from
+// about 3 * depth / |F| to 3 * depth / |E|
+#[derive(Clone)]
+pub struct ExtensionGrandProductProof<F: PrimeField, E: Field<BasePrimeField = F>> {
+    claim: F,
+    // The root's two children, still in F
+    top: Vec<F>,
+    // The two children of each reduced layer
+    evals: Vec<Vec<E>>,
+    sumcheck_proofs: Vec<ExtensionSumcheckProof<F, E>>,
+}
+
+impl<F: PrimeField, E: Field<BasePrimeField = F>> ExtensionGrandProductProof<F, E> {
+    // Returns the proof and the point z, ordered as in
+    // `GrandProductProof::prove`, at which the lifted witness MLE must equal
+    // the final claim
+    pub fn prove(
+        witness: &[F],
+        claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<E>) {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let tree = compute_tree(witness);
+        let top = tree[0].clone();
+        transcript.append_version();
+        transcript.append_scalar(b"grand_product_claim", &claim);
+        transcript.append_points(b"grand_product_top", &top);
+        let challenge = ext_challenge(b"grand_product_challenge", transcript);
+        let mut layer_claim = eval_mle(&[challenge], &lift(&top));
+        let mut z = vec![challenge];
+        let mut evals = vec![];
+        let mut sumcheck_proofs = vec![];
+        for layer in &tree[1..] {
+            let mles = core::iter::once(chis(&z))
+                .chain(factor(layer, 2).iter().map(|table| lift(table)))
+                .collect();
+            let sumcheck_proof =
+                ExtensionSumcheckProof::prove_lifted(layer_claim, mles, transcript);
+            let children = sumcheck_proof.final_terms[1..].to_vec();
+            absorb_ext(b"grand_product_point", &children, transcript);
+            let challenge = ext_challenge(b"grand_product_challenge", transcript);
+            layer_claim = eval_mle(&[challenge], &children);
+            z = sumcheck_proof.rands.clone();
+            z.push(challenge);
+            evals.push(children);
+            sumcheck_proofs.push(sumcheck_proof);
+        }
+        let proof = Self {
+            claim,
+            top,
+            evals,
+            sumcheck_proofs,
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(&z, start);
+        (proof, z)
+    }
+
+    // Replays `verify` from the transcript state the prover started with and
+    // checks it reaches the point the prover returned
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, point: &[E], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check(
+            "extension grand product",
+            || matches!(self.verify(self.claim, &mut transcript), Ok((_, z)) if z == point),
+        );
+    }
+
+    // Returns (final_claim, z); the caller must check the lifted witness MLE
+    // at z against final_claim
+    pub fn verify(
+        &self,
+        expected_product: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(E, Vec<E>), GrandProductError> {
+        if self.claim != expected_product {
+            return Err(GrandProductError::ClaimMismatch);
+        }
+        if self.top.len() != 2 || self.evals.len() != self.sumcheck_proofs.len() {
+            return Err(GrandProductError::MalformedProof);
+        }
+        if self.claim != self.top[0] * self.top[1] {
+            return Err(GrandProductError::RootMismatch);
+        }
+        transcript.append_version();
+        transcript.append_scalar(b"grand_product_claim", &self.claim);
+        transcript.append_points(b"grand_product_top", &self.top);
+        let challenge = ext_challenge(b"grand_product_challenge", transcript);
+        let mut claim = eval_mle(&[challenge], &lift(&self.top));
+        let mut z = vec![challenge];
+        for (i, (sumcheck_proof, children)) in
+            self.sumcheck_proofs.iter().zip(&self.evals).enumerate()
+        {
+            let layer = i + 1;
+            // Each layer's sumcheck runs over the variables fixed so far, on
+            // eq times the two children
+            if children.len() != 2 || sumcheck_proof.rounds != z.len() || sumcheck_proof.degree != 3
+            {
+                return Err(GrandProductError::MalformedProof);
+            }
+            if sumcheck_proof.claim != claim {
+                return Err(GrandProductError::LayerClaimMismatch { layer });
+            }
+            let (rands, expected) = sumcheck_proof
+                .verify(transcript)
+                .map_err(|_| GrandProductError::LayerClaimMismatch { layer })?;
+            if expected != eval_eq(&z, &rands) * children[0] * children[1] {
+                return Err(GrandProductError::LayerRelationMismatch { layer });
+            }
+            absorb_ext(b"grand_product_point", children, transcript);
+            let challenge = ext_challenge(b"grand_product_challenge", transcript);
+            claim = eval_mle(&[challenge], children);
             z = rands;
             z.push(challenge);
         }
-        (*self.claims.last().unwrap(), z)
+        Ok((claim, z))
     }
 }
 
@@ -130,8 +864,489 @@ fn grandproduct_test() {
     let claim = Fr::from(2 * 4 * 2 * 7);
 
     let mut transcript = Transcript::new(b"test_transcript");
-    let proof = GrandProductProof::prove(&v2, claim, &mut transcript);
+    let (proof, _) = GrandProductProof::prove(&v2, claim, &mut transcript);
     let mut vtranscript = Transcript::new(b"test_transcript");
-    let (final_claim, rands) = proof.verify(&mut vtranscript);
+    let (final_claim, rands) = proof.verify_unchecked(claim, &mut vtranscript);
     assert_eq!(final_claim, eval_mle(&rands, &v2));
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let result = std::panic::catch_unwind(move || {
+        proof.verify_unchecked(claim + Fr::from(1), &mut vtranscript);
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn grandproduct_merged_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+    for len in [8, 16, 32] {
+        let witness: Vec<Fr> = (1..=len).map(|i| Fr::from(i as u64)).collect();
+        let claim: Fr = witness.iter().product();
+
+        let mut transcript = Transcript::new(b"test_transcript");
+        let (proof, _) = GrandProductProof::prove(&witness, claim, &mut transcript);
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        let (final_claim, rands) = proof.verify_unchecked(claim, &mut vtranscript);
+        assert_eq!(final_claim, eval_mle(&rands, &witness));
+
+        let mut transcript = Transcript::new(b"test_transcript");
+        let (merged, _) = GrandProductProof::prove_merged(&witness, claim, &mut transcript);
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        let (merged_claim, merged_rands) = merged.verify_unchecked(claim, &mut vtranscript);
+        assert_eq!(merged_claim, eval_mle(&merged_rands, &witness));
+
+        assert_eq!(merged.claims[0], proof.claims[0]);
+        assert_eq!(merged_rands.len(), rands.len());
+        assert_eq!(
+            merged.sumcheck_proofs.len(),
+            proof.sumcheck_proofs.len().div_ceil(2)
+        );
+    }
+}
+
+#[test]
+fn grandproduct_final_point_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64)).collect();
+    let claim: Fr = witness.iter().product();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, point) = GrandProductProof::prove(&witness, claim, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (final_claim, z) = proof.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(point, z);
+    assert_eq!(z.len(), 4);
+    assert_eq!(final_claim, eval_mle(&point, &witness));
+}
+
+#[test]
+fn grandproduct_streaming_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=32).map(|i| Fr::from(i as u64)).collect();
+    let claim: Fr = witness.iter().product();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, point) = GrandProductProof::prove(&witness, claim, &mut transcript);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (streamed, streamed_point) =
+        GrandProductProof::prove_streaming(&witness, claim, &mut transcript);
+
+    assert_eq!(streamed.claims, proof.claims);
+    assert_eq!(streamed.evals, proof.evals);
+    assert_eq!(streamed_point, point);
+    for (a, b) in streamed.sumcheck_proofs.iter().zip(&proof.sumcheck_proofs) {
+        assert_eq!(a.polynomials, b.polynomials);
+        assert_eq!(a.final_terms, b.final_terms);
+    }
+    let (mut a, mut b) = (vec![], vec![]);
+    streamed.serialize_compressed(&mut a).unwrap();
+    proof.serialize_compressed(&mut b).unwrap();
+    assert_eq!(a, b);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (final_claim, z) = streamed.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(final_claim, eval_mle(&z, &witness));
+}
+
+#[test]
+fn grandproduct_verbose_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64)).collect();
+    let claim: Fr = witness.iter().product();
+    let tree = compute_tree(&witness);
+    for merged in [false, true] {
+        let mut transcript = Transcript::new(b"test_transcript");
+        let (proof, _) = if merged {
+            GrandProductProof::prove_merged(&witness, claim, &mut transcript)
+        } else {
+            GrandProductProof::prove(&witness, claim, &mut transcript)
+        };
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        let (final_claim, layers) = proof.verify_verbose(claim, &mut vtranscript).unwrap();
+        assert_eq!(layers[0], (claim, vec![]));
+        for (layer_claim, point) in layers.iter().skip(1) {
+            assert_eq!(*layer_claim, eval_mle(point, &tree[point.len() - 1]));
+        }
+        assert_eq!(layers.last().unwrap().0, final_claim);
+
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        assert_eq!(
+            proof
+                .verify_verbose(claim + Fr::from(1), &mut vtranscript)
+                .err(),
+            Some(GrandProductError::ClaimMismatch)
+        );
+    }
+}
+
+#[test]
+fn grandproduct_batched_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let rows: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64 + 3)).collect();
+    let cols: Vec<Fr> = (1..=16).map(|i| Fr::from(2 * i as u64 + 1)).collect();
+    let claims = vec![rows.iter().product(), cols.iter().product()];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) =
+        GrandProductProof::prove_batched(&[rows.clone(), cols.clone()], &claims, &mut transcript);
+    assert_eq!(proof.sumcheck_proofs.len(), 3);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let results = proof.verify_batched(&claims, &mut vtranscript).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].1, results[1].1);
+    assert_eq!(results[0].0, eval_mle(&results[0].1, &rows));
+    assert_eq!(results[1].0, eval_mle(&results[1].1, &cols));
+}
+
+#[test]
+fn grandproduct_batched_mixed_lengths_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let short: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64 + 3)).collect();
+    let long: Vec<Fr> = (1..=32).map(|i| Fr::from(2 * i as u64 + 1)).collect();
+    let claims = vec![short.iter().product(), long.iter().product()];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, points) =
+        GrandProductProof::prove_batched(&[short.clone(), long.clone()], &claims, &mut transcript);
+    // Layers 1..4 batch both trees, the last only the longer one
+    assert_eq!(proof.sumcheck_proofs.len(), 4);
+    assert_eq!(proof.sumcheck_proofs[3].final_terms.len(), 3);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let results = proof.verify_batched(&claims, &mut vtranscript).unwrap();
+    assert_eq!((results[0].1.len(), results[1].1.len()), (4, 5));
+    assert_eq!(results[0].1, points[0]);
+    assert_eq!(results[1].1, points[1]);
+    assert_eq!(results[0].0, eval_mle(&results[0].1, &short));
+    assert_eq!(results[1].0, eval_mle(&results[1].1, &long));
+
+    let mut tampered = proof.clone();
+    tampered.tops[1].swap(0, 1);
+    tampered.tops[1][0] *= Fr::from(3);
+    tampered.tops[1][1] *= Fr::from(3).inverse().unwrap();
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        tampered.verify_batched(&claims, &mut vtranscript).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof;
+    tampered.depths.swap(0, 1);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(tampered.verify_batched(&claims, &mut vtranscript).is_err());
+}
+
+#[test]
+fn grandproduct_padded_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=6).map(|i| Fr::from(i as u64)).collect();
+    let claim = Fr::from(720);
+    let tree = compute_tree(&witness);
+    assert_eq!(tree[0][0] * tree[0][1], claim);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) = GrandProductProof::prove(&witness, claim, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (final_claim, z) = proof.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(z.len(), 3);
+    assert_eq!(final_claim, eval_mle(&z, &pad_witness(&witness)));
+}
+
+#[test]
+fn fractional_grandproduct_test() {
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+    use merlin::Transcript;
+
+    let numerators: Vec<Fr> = (1..=8).map(|i| Fr::from(i as u64)).collect();
+    let denominators: Vec<Fr> = (1..=8).map(|i| Fr::from(3 * i as u64 + 2)).collect();
+    let tree = compute_fraction_tree(&numerators, &denominators);
+    let (p, q) = &tree[0];
+    let claim = (p[0] * q[1] + p[1] * q[0], q[0] * q[1]);
+    let brute: Fr = numerators
+        .iter()
+        .zip(&denominators)
+        .map(|(&n, &d)| n * d.inverse().unwrap())
+        .sum();
+    assert_eq!(claim.0 * claim.1.inverse().unwrap(), brute);
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof =
+        FractionalGrandProductProof::prove(&numerators, &denominators, claim, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (p_claim, q_claim, z) = proof.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(z.len(), 3);
+    assert_eq!(p_claim, eval_mle(&z, &numerators));
+    assert_eq!(q_claim, eval_mle(&z, &denominators));
+}
+
+#[test]
+fn compute_tree_test() {
+    use ark_curve25519::Fr;
+
+    let witness: Vec<Fr> = [2, 1, 2, 2, 2, 1, 7, 1]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let expected: Vec<Vec<Fr>> = vec![vec![8, 14], vec![2, 4, 2, 7], vec![2, 1, 2, 2, 2, 1, 7, 1]]
+        .into_iter()
+        .map(|layer| layer.into_iter().map(|x: u64| Fr::from(x)).collect())
+        .collect();
+    assert_eq!(compute_tree(&witness), expected);
+}
+
+#[test]
+fn grandproduct_serialize_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64)).collect();
+    let claim: Fr = witness.iter().product();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) = GrandProductProof::prove_merged(&witness, claim, &mut transcript);
+
+    let mut bytes = vec![];
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let decoded = GrandProductProof::<Fr>::deserialize_compressed(&bytes[..]).unwrap();
+    assert_eq!(decoded.claims, proof.claims);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (final_claim, z) = decoded.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(final_claim, eval_mle(&z, &witness));
+    let verify = |bytes: &[u8], claim| {
+        GrandProductProof::verify_from_bytes(bytes, claim, &mut Transcript::new(b"test_transcript"))
+    };
+    let (final_claim, z) = verify(&bytes, claim).unwrap();
+    assert_eq!(final_claim, eval_mle(&z, &witness));
+    assert_eq!(
+        verify(&bytes, claim + Fr::from(1)).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+    assert_eq!(
+        verify(&bytes[..bytes.len() - 1], claim).err(),
+        Some(GrandProductError::Undecodable)
+    );
+}
+
+#[test]
+fn grandproduct_weighted_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let values: Vec<Fr> = [3u64, 5, 7, 2, 11, 4, 9, 6].map(Fr::from).to_vec();
+    let exponents = [1, 0, 3, 7, 2, 1, 5, 4];
+    let mut claim = Fr::from(1u64);
+    for (v, &e) in values.iter().zip(&exponents) {
+        for _ in 0..e {
+            claim *= v;
+        }
+    }
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) = GrandProductProof::prove_weighted(&values, &exponents, claim, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (final_claim, z) = proof.verify_unchecked(claim, &mut vtranscript);
+    assert_eq!(
+        final_claim,
+        eval_mle(&z, &weighted_leaves(&values, &exponents))
+    );
+}
+
+#[test]
+fn grandproduct_tampered_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let witness: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64)).collect();
+    let claim: Fr = witness.iter().product();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) = GrandProductProof::prove(&witness, claim, &mut transcript);
+    let verify = |proof: &GrandProductProof<Fr>, claim| {
+        proof.verify(claim, &mut Transcript::new(b"test_transcript"))
+    };
+    assert_eq!(
+        verify(&proof, claim + Fr::from(1)).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+    let mut tampered = proof.clone();
+    tampered.evals[0][0] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::RootMismatch)
+    );
+    // A top that still multiplies out to the claim is bound before the
+    // challenge, so it no longer matches the first layer's sumcheck
+    let mut tampered = proof.clone();
+    tampered.evals[0][0] *= Fr::from(2);
+    tampered.evals[0][1] *= Fr::from(2).inverse().unwrap();
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof.clone();
+    tampered.evals[1][0] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerRelationMismatch { layer: 1 })
+    );
+    let mut tampered = proof.clone();
+    tampered.sumcheck_proofs[1].polynomials[0][0] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 2 })
+    );
+    let mut tampered = proof.clone();
+    tampered.evals.pop();
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::MalformedProof)
+    );
+    // A layer sumcheck with too few rounds would otherwise reach eval_eq
+    // with mismatched points
+    let mut tampered = proof;
+    tampered.sumcheck_proofs[1].polynomials.pop();
+    tampered.sumcheck_proofs[1].rounds -= 1;
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::MalformedProof)
+    );
+}
+
+#[test]
+fn grandproduct_batched_tampered_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let rows: Vec<Fr> = (1..=16).map(|i| Fr::from(i as u64 + 3)).collect();
+    let cols: Vec<Fr> = (1..=16).map(|i| Fr::from(2 * i as u64 + 1)).collect();
+    let claims = vec![rows.iter().product(), cols.iter().product()];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, _) = GrandProductProof::prove_batched(&[rows, cols], &claims, &mut transcript);
+    let verify = |proof: &BatchedGrandProductProof<Fr>, claims: &[Fr]| {
+        proof.verify_batched(claims, &mut Transcript::new(b"test_transcript"))
+    };
+    assert_eq!(
+        verify(&proof, &[claims[1], claims[0]]).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+    let mut tampered = proof.clone();
+    tampered.tops[1][0] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, &claims).err(),
+        Some(GrandProductError::RootMismatch)
+    );
+    // Tops that still multiply out to the claims are bound before the
+    // challenge, so they no longer match the first batched sumcheck
+    let mut tampered = proof.clone();
+    tampered.tops[0][0] *= Fr::from(2);
+    tampered.tops[0][1] *= Fr::from(2).inverse().unwrap();
+    assert_eq!(
+        verify(&tampered, &claims).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof.clone();
+    tampered.sumcheck_proofs[0].final_terms[1] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, &claims).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof;
+    tampered.tops.pop();
+    assert_eq!(
+        verify(&tampered, &claims).err(),
+        Some(GrandProductError::MalformedProof)
+    );
+}
+
+#[test]
+fn fractional_grandproduct_tampered_test() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let numerators: Vec<Fr> = (1..=8).map(|i| Fr::from(i as u64)).collect();
+    let denominators: Vec<Fr> = (1..=8).map(|i| Fr::from(3 * i as u64 + 2)).collect();
+    let (p, q) = &compute_fraction_tree(&numerators, &denominators)[0];
+    let claim = (p[0] * q[1] + p[1] * q[0], q[0] * q[1]);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof =
+        FractionalGrandProductProof::prove(&numerators, &denominators, claim, &mut transcript);
+    let verify = |proof: &FractionalGrandProductProof<Fr>, claim| {
+        proof.verify(claim, &mut Transcript::new(b"test_transcript"))
+    };
+    assert_eq!(
+        verify(&proof, (claim.0 + Fr::from(1), claim.1)).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+    let mut tampered = proof.clone();
+    tampered.top[2] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::RootMismatch)
+    );
+    // Scaling the first fraction's numerator and denominator by k and the
+    // second's by 1/k keeps the root pair, but the top is bound before the
+    // challenge
+    let mut tampered = proof.clone();
+    let (k, k_inv) = (Fr::from(2), Fr::from(2).inverse().unwrap());
+    tampered.top[0] *= k;
+    tampered.top[2] *= k;
+    tampered.top[1] *= k_inv;
+    tampered.top[3] *= k_inv;
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof;
+    tampered.sumcheck_proofs[0].final_terms[1] += Fr::from(1);
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerRelationMismatch { layer: 1 })
+    );
+}
+
+#[test]
+fn grandproduct_extension_test() {
+    use crate::fiatshamir::small_field::{Small, SmallExt};
+    use merlin::Transcript;
+
+    let witness: Vec<Small> = [3u64, 14, 15, 92, 65, 35, 89, 79].map(Small::from).to_vec();
+    let claim: Small = witness.iter().product();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, point) =
+        ExtensionGrandProductProof::<Small, SmallExt>::prove(&witness, claim, &mut transcript);
+    let verify = |proof: &ExtensionGrandProductProof<Small, SmallExt>, claim| {
+        proof.verify(claim, &mut Transcript::new(b"test_transcript"))
+    };
+    let (final_claim, z) = verify(&proof, claim).unwrap();
+    assert_eq!(z, point);
+    assert_eq!(final_claim, eval_mle(&z, &lift(&witness)));
+    // Challenges come from all 97^2 elements rather than the 97 of F
+    assert!(z.iter().any(|c| c.c1 != Small::from(0u64)));
+
+    assert_eq!(
+        verify(&proof, claim + Small::from(1u64)).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+    let mut tampered = proof.clone();
+    tampered.top.swap(0, 1);
+    tampered.top[0] *= Small::from(2u64);
+    tampered.top[1] *= Small::from(2u64).inverse().unwrap();
+    // The root still multiplies out, but the top is bound before the challenge
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerClaimMismatch { layer: 1 })
+    );
+    let mut tampered = proof;
+    tampered.evals[0][0] += SmallExt::ONE;
+    assert_eq!(
+        verify(&tampered, claim).err(),
+        Some(GrandProductError::LayerRelationMismatch { layer: 1 })
+    );
 }
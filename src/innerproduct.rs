@@ -0,0 +1,113 @@
+use alloc::vec;
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    multilinear::Opening,
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+pub struct InnerProductProof<F: PrimeField> {
+    sumcheck_proof: SumcheckProof<F>,
+    a_eval: F,
+    b_eval: F,
+}
+
+// Proves <a, b> == s. The caller must absorb commitments to a and b first
+pub fn prove<F: PrimeField>(
+    a: &[F],
+    b: &[F],
+    s: F,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> InnerProductProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(a.len(), b.len());
+    transcript.append_scalar(b"inner_product", &s);
+    let sumcheck_proof = SumcheckProof::prove(s, vec![a.to_vec(), b.to_vec()], transcript);
+    let (a_eval, b_eval) = (sumcheck_proof.final_terms[0], sumcheck_proof.final_terms[1]);
+    transcript.append_scalar(b"inner_product_eval", &a_eval);
+    transcript.append_scalar(b"inner_product_eval", &b_eval);
+    let proof = InnerProductProof {
+        sumcheck_proof,
+        a_eval,
+        b_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(a, b, s, start);
+    proof
+}
+
+impl<F: PrimeField> InnerProductProof<F> {
+    // Replays `verify` from the transcript state the prover started with and
+    // checks the openings it returns against a and b
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, a: &[F], b: &[F], s: F, mut transcript: impl ProtocolTranscript<F>) {
+        use crate::multilinear::eval_mle;
+
+        crate::selfverify::check("inner product", || {
+            matches!(
+                verify(a.len(), s, self, &mut transcript),
+                Ok(((ra, a_eval), (rb, b_eval)))
+                    if eval_mle(&ra, a) == a_eval && eval_mle(&rb, b) == b_eval
+            )
+        });
+    }
+}
+
+// Checks the proof is for the public s over vectors of length len and
+// returns the openings of a and b, which the caller must check against its
+// commitments
+pub fn verify<F: PrimeField>(
+    len: usize,
+    s: F,
+    proof: &InnerProductProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Opening<F>, Opening<F>), SumcheckError> {
+    transcript.append_scalar(b"inner_product", &s);
+    if proof.sumcheck_proof.claim != s {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    let (r, expected) = proof
+        .sumcheck_proof
+        .verify_shape(len.ilog2() as usize, 2, transcript)?;
+    if expected != proof.a_eval * proof.b_eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"inner_product_eval", &proof.a_eval);
+    transcript.append_scalar(b"inner_product_eval", &proof.b_eval);
+    Ok(((r.clone(), proof.a_eval), (r, proof.b_eval)))
+}
+
+#[test]
+fn test_inner_product() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 2)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(9 - i as u64)).collect();
+    let s: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, s, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((ra, a_eval), (rb, b_eval)) = verify(a.len(), s, &proof, &mut vtranscript).unwrap();
+    assert_eq!(a_eval, eval_mle(&ra, &a));
+    assert_eq!(b_eval, eval_mle(&rb, &b));
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(a.len(), s + Fr::from(1), &proof, &mut vtranscript).err(),
+        Some(SumcheckError::UnexpectedClaim)
+    );
+    // A proof over eight entries does not pass as one over four
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(4, s, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 3,
+            expected: 2
+        })
+    );
+}
@@ -1,6 +1,25 @@
-mod fiatshamir;
-mod grandproduct;
-mod matmul;
-mod multilinear;
-mod sumcheck;
-mod univariate;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod commitment;
+pub mod disjoint;
+pub mod eval;
+pub mod fiatshamir;
+pub mod field_utils;
+pub mod gkr;
+pub mod grandproduct;
+pub mod innerproduct;
+pub mod lookup;
+pub mod matmul;
+pub mod multilinear;
+pub mod multiset;
+pub mod permutation;
+pub mod prefixsum;
+pub mod r1cs;
+mod selfverify;
+pub mod sparsity;
+pub mod sumcheck;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod univariate;
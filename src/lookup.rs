@@ -0,0 +1,229 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    grandproduct::{BatchedGrandProductProof, GrandProductError, GrandProductProof},
+    multilinear::{eval_mle, eval_mle_streaming, Opening},
+};
+
+// Offline memory checking over a read-only table: each lookup reads
+// (addr, value, ts) and writes back (addr, value, ts + 1), and the table
+// starts at (i, table[i], 0) and ends at (i, table[i], final_ts[i]). Every
+// lookup hit the table iff init + writes == reads + final as multisets
+pub struct LookupProof<F: PrimeField> {
+    read_write: BatchedGrandProductProof<F>,
+    init_final: BatchedGrandProductProof<F>,
+    addr_eval: F,
+    value_eval: F,
+    ts_eval: F,
+    final_ts_eval: F,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    // init + writes and reads + final are different multisets
+    MultisetMismatch,
+    // A grand product's final claim disagrees with the fingerprint of the
+    // evaluations it should open to
+    FingerprintMismatch,
+    GrandProduct(GrandProductError),
+}
+
+// Maps (addr, value, ts) to addr * gamma^2 + value * gamma + ts - tau
+fn fingerprint<F: PrimeField>(addr: F, value: F, ts: F, gamma: F, tau: F) -> F {
+    addr * gamma.square() + value * gamma + ts - tau
+}
+
+fn to_field<F: PrimeField>(xs: &[usize]) -> Vec<F> {
+    xs.iter().map(|&x| F::from(x as u64)).collect()
+}
+
+// Proves table[lookups[k]] is the k-th looked up value. Both lengths must be
+// powers of two. The caller must absorb its commitments to the addresses,
+// values and read timestamps first
+pub fn prove<F: PrimeField>(
+    lookups: &[usize],
+    table: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> LookupProof<F> {
+    let values: Vec<F> = lookups.iter().map(|&a| table[a]).collect();
+    prove_reads(lookups, &values, table, transcript)
+}
+
+fn prove_reads<F: PrimeField>(
+    lookups: &[usize],
+    values: &[F],
+    table: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> LookupProof<F> {
+    let mut counts = vec![0; table.len()];
+    let read_ts: Vec<usize> = lookups
+        .iter()
+        .map(|&a| {
+            counts[a] += 1;
+            counts[a] - 1
+        })
+        .collect();
+    let (addrs, ts, final_ts) = (to_field(lookups), to_field(&read_ts), to_field(&counts));
+    let ids = to_field(&(0..table.len()).collect::<Vec<_>>());
+
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    transcript.append_usize(b"lookup_memory", table.len());
+    let gamma = transcript.challenge_scalar(b"lookup_gamma");
+    let tau = transcript.challenge_scalar(b"lookup_tau");
+    let h = |a: &[F], v: &[F], t: &[F], offset: F| -> Vec<F> {
+        (0..a.len())
+            .map(|i| fingerprint(a[i], v[i], t[i] + offset, gamma, tau))
+            .collect()
+    };
+    let read = h(&addrs, values, &ts, F::ZERO);
+    let write = h(&addrs, values, &ts, F::ONE);
+    let init = h(&ids, table, &vec![F::ZERO; table.len()], F::ZERO);
+    let fin = h(&ids, table, &final_ts, F::ZERO);
+    let products = |ws: &[Vec<F>]| -> Vec<F> { ws.iter().map(|w| w.iter().product()).collect() };
+
+    let rw = [read, write];
+    let (read_write, points) = GrandProductProof::prove_batched(&rw, &products(&rw), transcript);
+    let r = &points[0];
+    let (addr_eval, value_eval, ts_eval) =
+        (eval_mle(r, &addrs), eval_mle(r, values), eval_mle(r, &ts));
+    for eval in [addr_eval, value_eval, ts_eval] {
+        transcript.append_scalar(b"lookup_eval", &eval);
+    }
+    let inf = [init, fin];
+    let (init_final, points) = GrandProductProof::prove_batched(&inf, &products(&inf), transcript);
+    let final_ts_eval = eval_mle(&points[0], &final_ts);
+    transcript.append_scalar(b"lookup_eval", &final_ts_eval);
+    let proof = LookupProof {
+        read_write,
+        init_final,
+        addr_eval,
+        value_eval,
+        ts_eval,
+        final_ts_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(table, start);
+    proof
+}
+
+impl<F: PrimeField> LookupProof<F> {
+    // Replays the verifier from the transcript state the prover started
+    // with, catching absorb-order drift between the two at prove time
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, table: &[F], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("lookup", || verify(table, self, &mut transcript).is_ok());
+    }
+}
+
+// Checks the proof against the public table. Returns the openings of the
+// addresses, values and read timestamps at one point and of the final
+// timestamps at another, which the caller must check against its
+// commitments
+pub fn verify<F: PrimeField>(
+    table: &[F],
+    proof: &LookupProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<[Opening<F>; 4], LookupError> {
+    transcript.append_usize(b"lookup_memory", table.len());
+    let gamma = transcript.challenge_scalar(b"lookup_gamma");
+    let tau = transcript.challenge_scalar(b"lookup_tau");
+    let (rw, inf) = (proof.read_write.claims(), proof.init_final.claims());
+    if (rw.len(), inf.len()) != (2, 2) {
+        return Err(LookupError::GrandProduct(GrandProductError::MalformedProof));
+    }
+    if inf[0] * rw[1] != rw[0] * inf[1] {
+        return Err(LookupError::MultisetMismatch);
+    }
+
+    let claims = proof
+        .read_write
+        .verify_batched(rw, transcript)
+        .map_err(LookupError::GrandProduct)?;
+    let r = claims[0].1.clone();
+    let read = fingerprint(proof.addr_eval, proof.value_eval, proof.ts_eval, gamma, tau);
+    // Write timestamps are the read ones plus one everywhere
+    if claims[0].0 != read || claims[1].0 != read + F::ONE {
+        return Err(LookupError::FingerprintMismatch);
+    }
+    for eval in [proof.addr_eval, proof.value_eval, proof.ts_eval] {
+        transcript.append_scalar(b"lookup_eval", &eval);
+    }
+
+    let claims = proof
+        .init_final
+        .verify_batched(inf, transcript)
+        .map_err(LookupError::GrandProduct)?;
+    let s = claims[0].1.clone();
+    if s.len() != table.len().ilog2() as usize {
+        return Err(LookupError::GrandProduct(GrandProductError::MalformedProof));
+    }
+    let ids = to_field(&(0..table.len()).collect::<Vec<_>>());
+    let (id_eval, table_eval) = (eval_mle_streaming(&s, &ids), eval_mle_streaming(&s, table));
+    if claims[0].0 != fingerprint(id_eval, table_eval, F::ZERO, gamma, tau)
+        || claims[1].0 != fingerprint(id_eval, table_eval, proof.final_ts_eval, gamma, tau)
+    {
+        return Err(LookupError::FingerprintMismatch);
+    }
+    transcript.append_scalar(b"lookup_eval", &proof.final_ts_eval);
+    Ok([
+        (r.clone(), proof.addr_eval),
+        (r.clone(), proof.value_eval),
+        (r, proof.ts_eval),
+        (s, proof.final_ts_eval),
+    ])
+}
+
+#[test]
+fn test_lookup() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let table: Vec<Fr> = (0..16u64).map(|i| Fr::from(i * i + 1)).collect();
+    let lookups = [3, 0, 15, 3, 7, 7, 7, 12];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&lookups, &table, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let [(r, addr_eval), (_, value_eval), _, _] = verify(&table, &proof, &mut vtranscript).unwrap();
+    let values: Vec<Fr> = lookups.iter().map(|&a| table[a]).collect();
+    assert_eq!(addr_eval, eval_mle(&r, &to_field(&lookups)));
+    assert_eq!(value_eval, eval_mle(&r, &values));
+}
+
+// A dishonest prover, so its proof skips self-verify
+#[test]
+fn test_lookup_missing_value() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let table: Vec<Fr> = (0..16u64).map(|i| Fr::from(i * i + 1)).collect();
+    let lookups = [3, 0, 15, 3, 7, 7, 7, 12];
+    let mut values: Vec<Fr> = lookups.iter().map(|&a| table[a]).collect();
+    values[4] = Fr::from(1000u64);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof =
+        crate::selfverify::skipped(|| prove_reads(&lookups, &values, &table, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(&table, &proof, &mut vtranscript).err(),
+        Some(LookupError::MultisetMismatch)
+    );
+}
+
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+#[test]
+#[should_panic(expected = "lookup self-verify failed")]
+fn test_lookup_self_verify_fires() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let table: Vec<Fr> = (0..16u64).map(Fr::from).collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let mut desynced = transcript.clone();
+    let proof = prove(&[1, 2, 3, 4], &table, &mut transcript);
+    // A verifier that absorbs one message the prover never did
+    desynced.append_message(b"lookup_desynced", b"");
+    proof.self_verify(&table, desynced);
+}
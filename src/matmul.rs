@@ -1,65 +1,447 @@
-use ark_ff::{BigInteger, PrimeField};
-use itertools::Itertools;
-use merlin::Transcript;
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
 
 use crate::{
     fiatshamir::ProtocolTranscript,
-    multilinear::{chis, eval_chis, eval_mle, set_variable, set_variable_second_half},
-    sumcheck::{self, SumcheckProof},
-    univariate::eval_ule,
+    multilinear::{
+        chis, eval_eq, eval_mle, eval_mle_streaming, set_variable, set_variable_second_half,
+    },
+    sumcheck::{SumcheckError, SumcheckProof},
 };
 
-pub fn prove<F: PrimeField + From<i32>>(
+// Proves c = a * b for a m x n, b n x p and c m x p, all row-major with
+// power-of-two dimensions
+pub fn prove<F: PrimeField>(
     a: &[F],
     b: &[F],
     c: &[F],
+    (m, n, p): (usize, usize, usize),
     transcript: &mut impl ProtocolTranscript<F>,
 ) -> SumcheckProof<F> {
-    let r_len = (c.len().ilog2() / 2) as usize;
-    transcript.append_points(b"mat_mult_a", &a);
-    transcript.append_points(b"mat_mult_b", &b);
-    transcript.append_points(b"mat_mult_c", &c);
-    let r1 = transcript.challenge_scalars(b"mat_mult_r1", r_len);
-    let r2 = transcript.challenge_scalars(b"mat_mult_r2", r_len);
+    assert_eq!((a.len(), b.len(), c.len()), (m * n, n * p, m * p));
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    transcript.append_points(b"mat_mult_a", a);
+    transcript.append_points(b"mat_mult_b", b);
+    transcript.append_points(b"mat_mult_c", c);
+    let r1 = transcript.challenge_scalars(b"mat_mult_r1", m.ilog2() as usize);
+    let r2 = transcript.challenge_scalars(b"mat_mult_r2", p.ilog2() as usize);
+    // a's row variables come first, b's column variables last; the latter
+    // are bound from the least significant so r2 ends in its natural order
     let fa = r1.iter().fold(a.to_vec(), |a, &r| set_variable(&a, r));
     let fb: Vec<F> = r2
         .iter()
+        .rev()
         .fold(b.to_vec(), |b, &r| set_variable_second_half(&b, r));
-    let r: Vec<F> = r1.into_iter().chain(r2.into_iter()).collect();
-    let claim = eval_mle(&r, &c);
+    let r: Vec<F> = r1.into_iter().chain(r2).collect();
+    let claim = eval_mle(&r, c);
     let proof = SumcheckProof::prove(claim, vec![fa, fb], transcript);
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    self_verify([a, b, c], (m, n, p), &proof, start);
     proof
 }
 
-pub fn verify<F: PrimeField + From<i32>>(
+// Replays `verify` from the transcript state the prover started with; a c
+// that is not a * b fails here
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+fn self_verify<F: PrimeField>(
+    [a, b, c]: [&[F]; 3],
+    dims: (usize, usize, usize),
+    proof: &SumcheckProof<F>,
+    mut transcript: impl ProtocolTranscript<F>,
+) {
+    crate::selfverify::check("matmul", || {
+        verify(a, b, c, dims, proof.clone(), &mut transcript).is_ok()
+    });
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatmulError {
+    DimensionMismatch,
+    MalformedProof,
+    OpeningMismatch,
+    Sumcheck(SumcheckError),
+}
+
+// Returns the points a and b were opened at and the product of their
+// evaluations there, for callers that check the openings against
+// commitments instead
+pub fn verify<F: PrimeField>(
     a: &[F],
     b: &[F],
     c: &[F],
+    (m, n, p): (usize, usize, usize),
     sumcheck_proof: SumcheckProof<F>,
     transcript: &mut impl ProtocolTranscript<F>,
-) {
-    let r_len = (c.len().ilog2() / 2) as usize;
-    transcript.append_points(b"mat_mult_a", &a);
-    transcript.append_points(b"mat_mult_b", &b);
-    transcript.append_points(b"mat_mult_c", &c);
-    let r1 = transcript.challenge_scalars(b"mat_mult_r1", r_len);
-    let r2 = transcript.challenge_scalars(b"mat_mult_r2", r_len);
-    let (r3, expected_eval) = SumcheckProof::verify(&sumcheck_proof, transcript);
+) -> Result<(Vec<F>, Vec<F>, F), MatmulError> {
+    if ![m, n, p].iter().all(|d| d.is_power_of_two())
+        || (a.len(), b.len(), c.len()) != (m * n, n * p, m * p)
+    {
+        return Err(MatmulError::DimensionMismatch);
+    }
+    transcript.append_points(b"mat_mult_a", a);
+    transcript.append_points(b"mat_mult_b", b);
+    transcript.append_points(b"mat_mult_c", c);
+    let r1 = transcript.challenge_scalars(b"mat_mult_r1", m.ilog2() as usize);
+    let r2 = transcript.challenge_scalars(b"mat_mult_r2", p.ilog2() as usize);
+    let r: Vec<F> = r1.iter().chain(&r2).cloned().collect();
+    if sumcheck_proof.claim != eval_mle_streaming(&r, c) {
+        return Err(MatmulError::OpeningMismatch);
+    }
+    // The proof runs over n's variables with a degree-2 product; anything
+    // else would open a and b at mis-sized points
+    if sumcheck_proof.rounds != n.ilog2() as usize || sumcheck_proof.degree != 2 {
+        return Err(MatmulError::MalformedProof);
+    }
+    let (r3, expected_eval) = sumcheck_proof
+        .verify(transcript)
+        .map_err(MatmulError::Sumcheck)?;
+
+    let fa_r: Vec<F> = r1.into_iter().chain(r3.clone()).collect();
+    let fb_r: Vec<F> = r3.into_iter().chain(r2).collect();
+    if expected_eval != eval_mle_streaming(&fa_r, a) * eval_mle_streaming(&fb_r, b) {
+        return Err(MatmulError::OpeningMismatch);
+    }
+    Ok((fa_r, fb_r, expected_eval))
+}
+
+// The side of the row-major square matrix held in m_len entries. Points
+// split evenly into row and column variables, so the side must be a power
+// of two
+fn square_side(m_len: usize, context: &str) -> usize {
+    let side = m_len.isqrt();
+    assert!(
+        side * side == m_len && side.is_power_of_two(),
+        "{}: expected a square matrix with power-of-two side, got {} entries",
+        context,
+        m_len
+    );
+    side
+}
+
+// Proves M == M^T by opening M at a random (rx, ry) and at (ry, rx). The
+// caller must absorb its commitment to M first and check both openings
+pub fn prove_symmetric<F: PrimeField>(m: &[F], transcript: &mut impl ProtocolTranscript<F>) -> F {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    let r_len = square_side(m.len(), "prove_symmetric").ilog2() as usize;
+    transcript.append_version();
+    let rx = transcript.challenge_scalars(b"mat_sym_rx", r_len);
+    let ry = transcript.challenge_scalars(b"mat_sym_ry", r_len);
+    let r: Vec<F> = rx.into_iter().chain(ry).collect();
+    let eval = eval_mle(&r, m);
+    transcript.append_scalar(b"mat_sym_eval", &eval);
+    // Replays `verify_symmetric` from the transcript state the prover
+    // started with; an asymmetric m opens differently at the transposed point
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    crate::selfverify::check("symmetric", || {
+        let mut transcript = start;
+        let (point, transposed) = verify_symmetric(m.len(), eval, &mut transcript);
+        eval_mle(&point, m) == eval && eval_mle(&transposed, m) == eval
+    });
+    eval
+}
+
+// Returns the points (rx, ry) and (ry, rx) at which M must open to eval
+pub fn verify_symmetric<F: PrimeField>(
+    m_len: usize,
+    eval: F,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> (Vec<F>, Vec<F>) {
+    let r_len = square_side(m_len, "verify_symmetric").ilog2() as usize;
+    transcript.append_version();
+    let rx = transcript.challenge_scalars(b"mat_sym_rx", r_len);
+    let ry = transcript.challenge_scalars(b"mat_sym_ry", r_len);
+    transcript.append_scalar(b"mat_sym_eval", &eval);
+    let point: Vec<F> = rx.iter().chain(&ry).cloned().collect();
+    let transposed: Vec<F> = ry.into_iter().chain(rx).collect();
+    (point, transposed)
+}
+
+// Table of the n x n selector that is 1 at (i, j) when i < j, row-major
+fn lt_table<F: PrimeField>(n: usize) -> Vec<F> {
+    (0..n * n)
+        .map(|k| if k / n < k % n { F::ONE } else { F::ZERO })
+        .collect()
+}
+
+// Closed form of the MLE of lt_table with big-endian bits: x < y iff they
+// agree on a prefix and then x has a 0 where y has a 1
+pub fn eval_lt<F: PrimeField>(x: &[F], y: &[F]) -> F {
+    assert_eq!(x.len(), y.len());
+    let mut prefix_eq = F::ONE;
+    let mut total = F::ZERO;
+    for (&a, &b) in x.iter().zip(y) {
+        total += prefix_eq * (F::ONE - a) * b;
+        prefix_eq *= a * b + (F::ONE - a) * (F::ONE - b);
+    }
+    total
+}
+
+pub struct TriangularProof<F: PrimeField> {
+    sumcheck_proof: SumcheckProof<F>,
+    eval: F,
+}
+
+// Proves M[i][j] == 0 for j > i of the row-major n x n matrix M by showing
+// the eq-weighted sum of lt(i, j) * M[i][j] vanishes at a random point. The
+// caller must absorb its commitment to M first
+pub fn prove_lower_triangular<F: PrimeField>(
+    m: &[F],
+    n: usize,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> TriangularProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(
+        square_side(m.len(), "prove_lower_triangular"),
+        n,
+        "prove_lower_triangular: matrix is not {} x {}",
+        n,
+        n
+    );
+    let num_vars = 2 * n.ilog2() as usize;
+    let r = transcript.challenge_scalars(b"mat_tri_r", num_vars);
+    let mles = vec![chis(&r), lt_table(n), m.to_vec()];
+    let sumcheck_proof = SumcheckProof::prove(F::ZERO, mles, transcript);
+    let eval = sumcheck_proof.final_terms[2];
+    transcript.append_scalar(b"mat_tri_eval", &eval);
+    let proof = TriangularProof {
+        sumcheck_proof,
+        eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(m, n, start);
+    proof
+}
 
-    let fa_r: Vec<F> = r1.into_iter().chain(r3.clone().into_iter()).collect();
-    let fb_r: Vec<F> = r3.into_iter().chain(r2.into_iter()).collect();
-    assert_eq!(expected_eval, eval_mle(&fa_r, &a) * eval_mle(&fb_r, &b));
+impl<F: PrimeField> TriangularProof<F> {
+    // Replays `verify_lower_triangular` from the transcript state the prover
+    // started with and checks the opening it returns against m
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, m: &[F], n: usize, mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("lower triangular", || {
+            matches!(
+                verify_lower_triangular(n, self, &mut transcript),
+                Ok((s, eval)) if eval_mle(&s, m) == eval
+            )
+        });
+    }
+}
+
+// Returns the point at which the n x n matrix M must open to the returned
+// evaluation
+pub fn verify_lower_triangular<F: PrimeField>(
+    n: usize,
+    proof: &TriangularProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Vec<F>, F), SumcheckError> {
+    assert!(
+        n.is_power_of_two(),
+        "verify_lower_triangular: side {} is not a power of two",
+        n
+    );
+    let num_vars = 2 * n.ilog2() as usize;
+    let r = transcript.challenge_scalars(b"mat_tri_r", num_vars);
+    // The upper triangle must sum to zero
+    if proof.sumcheck_proof.claim != F::ZERO {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    // eq * lt * M over the row and column variables
+    let (s, expected) = proof.sumcheck_proof.verify_shape(num_vars, 3, transcript)?;
+    let (sx, sy) = s.split_at(num_vars / 2);
+    if expected != eval_eq(&r, &s) * eval_lt(sx, sy) * proof.eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"mat_tri_eval", &proof.eval);
+    Ok((s, proof.eval))
 }
 
 #[test]
 fn matrix() {
     use ark_curve25519::Fr;
+    use merlin::Transcript;
 
     let a = vec![Fr::from(1), Fr::from(0), Fr::from(0), Fr::from(1)];
     let b = vec![Fr::from(4), Fr::from(1), Fr::from(2), Fr::from(2)];
     let c = vec![Fr::from(4), Fr::from(1), Fr::from(2), Fr::from(2)];
     let mut transcript = Transcript::new(b"test_transcript");
-    let proof = prove(&a, &b, &c, &mut transcript);
+    let proof = prove(&a, &b, &c, (2, 2, 2), &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(verify(&a, &b, &c, (2, 2, 2), proof, &mut vtranscript).is_ok());
+}
+
+#[test]
+fn rectangular() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let to_field = |v: &[u64]| v.iter().map(|&x| Fr::from(x)).collect::<Vec<_>>();
+    // 2x4 times 4x2
+    let a = to_field(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let b = to_field(&[1, 0, 2, 1, 0, 3, 1, 1]);
+    let c = to_field(&[9, 15, 25, 35]);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, &c, (2, 4, 2), &mut transcript);
+    assert_eq!(proof.rounds, 2);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (fa_r, fb_r, eval) = verify(&a, &b, &c, (2, 4, 2), proof, &mut vtranscript).unwrap();
+    assert_eq!((fa_r.len(), fb_r.len()), (3, 3));
+    assert_eq!(eval, eval_mle(&fa_r, &a) * eval_mle(&fb_r, &b));
+
+    let wrong = to_field(&[9, 15, 25, 36]);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, &c, (2, 4, 2), &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(&a, &b, &wrong, (2, 4, 2), proof.clone(), &mut vtranscript),
+        Err(MatmulError::OpeningMismatch)
+    );
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(&a, &b, &c, (2, 2, 4), proof, &mut vtranscript),
+        Err(MatmulError::DimensionMismatch)
+    );
+
+    // 4x4 squared, where the column variables of b need their order kept
+    let a: Vec<Fr> = (1..=16).map(|x| Fr::from(x as u64)).collect();
+    let c: Vec<Fr> = (0..16)
+        .map(|ij| (0..4).map(|k| a[ij / 4 * 4 + k] * a[k * 4 + ij % 4]).sum())
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &a, &c, (4, 4, 4), &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(verify(&a, &a, &c, (4, 4, 4), proof, &mut vtranscript).is_ok());
+}
+
+#[test]
+fn symmetric() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    // 3x3 symmetric matrix embedded in a 4x4 table
+    let rows = [[1, 2, 3], [2, 5, 6], [3, 6, 9]];
+    let mut padded = vec![Fr::from(0); 16];
+    for i in 0..3 {
+        for j in 0..3 {
+            padded[i * 4 + j] = Fr::from(rows[i][j] as u64);
+        }
+    }
+    let mut transcript = Transcript::new(b"test_transcript");
+    let eval = prove_symmetric(&padded, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (point, transposed) = verify_symmetric(padded.len(), eval, &mut vtranscript);
+    assert_eq!(eval_mle(&point, &padded), eval);
+    assert_eq!(eval_mle(&transposed, &padded), eval);
+
+    // An asymmetric matrix, so the proof skips self-verify
+    let asymmetric = vec![Fr::from(1), Fr::from(2), Fr::from(3), Fr::from(4)];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let eval = crate::selfverify::skipped(|| prove_symmetric(&asymmetric, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (point, transposed) = verify_symmetric(asymmetric.len(), eval, &mut vtranscript);
+    assert_eq!(eval_mle(&point, &asymmetric), eval);
+    assert_ne!(eval_mle(&transposed, &asymmetric), eval);
+}
+
+#[test]
+#[should_panic(
+    expected = "prove_symmetric: expected a square matrix with power-of-two side, got 8 entries"
+)]
+fn symmetric_not_square() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    // 2x4 has a power-of-two length but is not square
+    let m = vec![Fr::from(1); 8];
+    prove_symmetric(&m, &mut Transcript::new(b"test_transcript"));
+}
+
+#[test]
+fn lower_triangular() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let point: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64 + 5)).collect();
+    assert_eq!(
+        eval_lt(&point[..2], &point[2..]),
+        eval_mle(&point, &lt_table::<Fr>(4))
+    );
+
+    let m: Vec<Fr> = [1, 0, 0, 0, 2, 3, 0, 0, 4, 5, 6, 0, 7, 8, 9, 10]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_lower_triangular(&m, 4, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (s, eval) = verify_lower_triangular(4, &proof, &mut vtranscript).unwrap();
+    assert_eq!(eval, eval_mle(&s, &m));
+
+    // A proof for a 4x4 matrix does not pass as one for 2x2
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_lower_triangular(2, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 4,
+            expected: 2
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "prove_lower_triangular: matrix is not 2 x 2")]
+fn lower_triangular_wrong_side() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let m = vec![Fr::from(1); 16];
+    prove_lower_triangular(&m, 2, &mut Transcript::new(b"test_transcript"));
+}
+
+// A dishonest prover, so its proof skips self-verify
+#[test]
+fn not_lower_triangular() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let m: Vec<Fr> = [1, 0, 0, 0, 2, 3, 0, 1, 4, 5, 6, 0, 7, 8, 9, 10]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_lower_triangular(&m, 4, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_lower_triangular(4, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::FinalEvalMismatch)
+    );
+}
+
+#[test]
+fn tampered() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (1..=16).map(|x| Fr::from(x as u64)).collect();
+    let c: Vec<Fr> = (0..16)
+        .map(|ij| (0..4).map(|k| a[ij / 4 * 4 + k] * a[k * 4 + ij % 4]).sum())
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let mut proof = prove(&a, &a, &c, (4, 4, 4), &mut transcript);
+    let mut truncated = proof.clone();
+    proof.polynomials[1][0] += Fr::from(1);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(matches!(
+        verify(&a, &a, &c, (4, 4, 4), proof, &mut vtranscript),
+        Err(MatmulError::Sumcheck(SumcheckError::ClaimMismatch { .. }))
+    ));
+
+    truncated.polynomials.pop();
+    truncated.rounds = 1;
     let mut vtranscript = Transcript::new(b"test_transcript");
-    let rs = verify(&a, &b, &c, proof, &mut vtranscript);
+    assert_eq!(
+        verify(&a, &a, &c, (4, 4, 4), truncated, &mut vtranscript),
+        Err(MatmulError::MalformedProof)
+    );
 }
@@ -1,6 +1,18 @@
-use ark_ff::PrimeField;
+use alloc::{vec, vec::Vec};
+use ark_ff::{Field, PrimeField};
 
-pub fn chis<F: PrimeField>(point: &[F]) -> Vec<F> {
+// A claimed evaluation of an MLE at a point
+pub type Opening<F> = (Vec<F>, F);
+
+pub fn chis<F: Field>(point: &[F]) -> Vec<F> {
+    #[cfg(feature = "parallel")]
+    if 1 << point.len() >= PAR_THRESHOLD {
+        return chis_par(point);
+    }
+    chis_serial(point)
+}
+
+fn chis_serial<F: Field>(point: &[F]) -> Vec<F> {
     point.iter().fold(vec![F::ONE], |table, &r| {
         table
             .iter()
@@ -9,38 +21,539 @@ pub fn chis<F: PrimeField>(point: &[F]) -> Vec<F> {
     })
 }
 
-pub fn eval_eq<F: PrimeField>(a: &[F], b: &[F]) -> F {
+// Same table as `chis`, built in place by binding variables from the last
+// (least significant) to the first so each round writes the upper half
+// from the lower half in parallel
+#[cfg(feature = "parallel")]
+pub fn chis_par<F: Field>(point: &[F]) -> Vec<F> {
+    use rayon::prelude::*;
+    let mut table = vec![F::ZERO; 1 << point.len()];
+    table[0] = F::ONE;
+    for (i, &r) in point.iter().rev().enumerate() {
+        let (lo, hi) = table[..2 << i].split_at_mut(1 << i);
+        lo.par_iter_mut().zip(hi.par_iter_mut()).for_each(|(l, h)| {
+            *h = r * *l;
+            *l *= F::ONE - r;
+        });
+    }
+    table
+}
+
+pub fn eval_eq<F: Field>(a: &[F], b: &[F]) -> F {
     (0..a.len())
         .map(|i| a[i] * b[i] + (F::one() - a[i]) * (F::one() - b[i]))
         .product()
 }
 
-pub fn eval_chis<F: PrimeField>(chis: &[F], evals: &[F]) -> F {
+// eq over the first min(a.len(), b.len()) coordinates only; the longer
+// point's extra trailing coordinates are ignored rather than rejected
+pub fn eval_eq_prefix<F: PrimeField>(a: &[F], b: &[F]) -> F {
+    let n = a.len().min(b.len());
+    eval_eq(&a[..n], &b[..n])
+}
+
+// Big-endian bits of index as field elements, matching the `chis` ordering
+pub fn index_to_bits<F: PrimeField>(index: usize, num_vars: usize) -> Vec<F> {
+    (0..num_vars)
+        .rev()
+        .map(|j| F::from(((index >> j) & 1) as u64))
+        .collect()
+}
+
+// The equality table of point: entry i is eq(point, index_to_bits(i, n)),
+// so it can be indexed directly (see `chi_at`) instead of re-deriving one
+// equality value per index. Same table as `chis`
+pub fn eq_table<F: PrimeField>(point: &[F]) -> Vec<F> {
+    chis(point)
+}
+
+// Tables shorter than this stay serial even with the `parallel` feature,
+// since spawning rayon tasks for small MLEs costs more than it saves
+pub const PAR_THRESHOLD: usize = 1 << 10;
+
+pub fn eval_chis<F: Field>(chis: &[F], evals: &[F]) -> F {
     assert_eq!(chis.len(), evals.len());
+    #[cfg(feature = "parallel")]
+    if chis.len() >= PAR_THRESHOLD {
+        use rayon::prelude::*;
+        return chis.par_iter().zip(evals).map(|(&a, &b)| a * b).sum();
+    }
     chis.iter().zip(evals).map(|(&a, &b)| a * b).sum()
 }
 
-pub fn eval_mle<F: PrimeField>(point: &[F], evals: &[F]) -> F {
+// eval_chis against the i-th basis vector, which is just the i-th entry
+pub fn chi_at<F: PrimeField>(chis: &[F], index: usize) -> F {
+    assert!(
+        index < chis.len(),
+        "chi_at: index {} out of range for {} entries",
+        index,
+        chis.len()
+    );
+    chis[index]
+}
+
+pub fn eval_mle<F: Field>(point: &[F], evals: &[F]) -> F {
+    assert_power_of_two(evals, "eval_mle");
     eval_chis(&chis(point), evals)
 }
 
+// Same value as `eval_mle` without the 2^n `chis` table: the weights are
+// kept as running prefix products over the point's coordinates, and moving
+// to the next index only recomputes the coordinates whose bits flipped.
+// Extra space is n + 1 field elements and the total work is about 2 * 2^n
+// multiplications. Suits verifiers that would throw the table away
+pub fn eval_mle_streaming<F: PrimeField>(point: &[F], evals: &[F]) -> F {
+    assert_power_of_two(evals, "eval_mle_streaming");
+    let n = point.len();
+    assert_eq!(evals.len(), 1 << n);
+    let mut prefix = vec![F::ONE; n + 1];
+    for (k, &r) in point.iter().enumerate() {
+        prefix[k + 1] = prefix[k] * (F::ONE - r);
+    }
+    let mut sum = prefix[n] * evals[0];
+    for (i, &eval) in evals.iter().enumerate().skip(1) {
+        // Incrementing flips the low trailing_zeros + 1 bits, which are the
+        // last coordinates of the point
+        let start = n - 1 - i.trailing_zeros() as usize;
+        for k in start..n {
+            let r = point[k];
+            let factor = if (i >> (n - 1 - k)) & 1 == 1 {
+                r
+            } else {
+                F::ONE - r
+            };
+            prefix[k + 1] = prefix[k] * factor;
+        }
+        sum += prefix[n] * eval;
+    }
+    sum
+}
+
+// Evaluates one table at many points, rebuilding the `chis` table for each
+// point in a single reused buffer
+pub fn eval_mle_batch<F: PrimeField>(points: &[Vec<F>], evals: &[F]) -> Vec<F> {
+    let mut table = Vec::with_capacity(evals.len());
+    points
+        .iter()
+        .map(|point| {
+            chis_into(point, &mut table);
+            eval_chis(&table, evals)
+        })
+        .collect()
+}
+
+// Writes `chis(point)` into table, binding from the last variable so each
+// round extends the table in place
+fn chis_into<F: Field>(point: &[F], table: &mut Vec<F>) {
+    table.clear();
+    table.resize(1 << point.len(), F::ZERO);
+    table[0] = F::ONE;
+    for (i, &r) in point.iter().rev().enumerate() {
+        let (lo, hi) = table[..2 << i].split_at_mut(1 << i);
+        for (l, h) in lo.iter_mut().zip(hi.iter_mut()) {
+            *h = r * *l;
+            *l *= F::ONE - r;
+        }
+    }
+}
+
 pub fn pad_next_power_of_two<F: PrimeField>(terms: &[F]) -> Vec<F> {
     let next = terms.len().next_power_of_two();
     let pad = vec![F::ZERO; next - terms.len()];
     terms.iter().cloned().chain(pad).collect()
 }
 
+// Tables are indexed by hypercube points, so anything else would be
+// silently truncated when halved
+pub fn assert_power_of_two<F>(v: &[F], context: &str) {
+    assert!(
+        v.len().is_power_of_two(),
+        "{}: expected power-of-two table, got len {}",
+        context,
+        v.len()
+    );
+}
+
+// Which end of the point a variable is bound at. Low binds point[0], the
+// most significant index bit, by folding the table's two halves together;
+// High binds the last coordinate by folding adjacent pairs. Sumcheck, the
+// grand products and GKR bind Low; matmul binds b's column variables High
+// so r2 keeps its natural order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindOrder {
+    Low,
+    High,
+}
+
+pub fn bind_variable<F: PrimeField>(mle: &[F], r: F, order: BindOrder) -> Vec<F> {
+    assert_power_of_two(mle, "bind_variable");
+    let fold = |a: F, b: F| (F::ONE - r) * a + r * b;
+    match order {
+        BindOrder::Low => {
+            let (a, b) = mle.split_at(mle.len() / 2);
+            a.iter().zip(b).map(|(&a, &b)| fold(a, b)).collect()
+        }
+        BindOrder::High => mle.chunks(2).map(|a| fold(a[0], a[1])).collect(),
+    }
+}
+
 pub fn set_variable<F: PrimeField>(mle: &[F], r: F) -> Vec<F> {
+    assert_power_of_two(mle, "set_variable");
+    bind_variable(mle, r, BindOrder::Low)
+}
+
+// Binds the leading variables to prefix, leaving the trailing ones free
+pub fn partial_eval<F: PrimeField>(evals: &[F], prefix: &[F]) -> Vec<F> {
+    assert!(1 << prefix.len() <= evals.len());
+    prefix
+        .iter()
+        .fold(evals.to_vec(), |table, &r| set_variable(&table, r))
+}
+
+// Same as `set_variable` but folds into the first half and truncates,
+// reusing the allocation
+pub fn fix_variable_in_place<F: Field>(mle: &mut Vec<F>, r: F) {
     let half = mle.len() / 2;
-    let (a, b) = mle.split_at(half);
-    a.iter()
-        .zip(b)
-        .map(|(&a, &b)| (F::ONE - r) * a + r * b)
-        .collect()
+    let (a, b) = mle.split_at_mut(half);
+    for (a, &b) in a.iter_mut().zip(b.iter()) {
+        *a = (F::ONE - r) * *a + r * b;
+    }
+    mle.truncate(half);
 }
 
 pub fn set_variable_second_half<F: PrimeField>(mle: &[F], r: F) -> Vec<F> {
-    mle.chunks(2)
-        .map(|a| (F::ONE - r) * a[0] + r * a[1])
-        .collect()
+    bind_variable(mle, r, BindOrder::High)
+}
+
+// An MLE held as its evaluations over the hypercube, in the same big-endian
+// order as `chis`. Construction checks the table is a power of two long
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultilinearPolynomial<F: PrimeField> {
+    evals: Vec<F>,
+    num_vars: usize,
+}
+
+impl<F: PrimeField> MultilinearPolynomial<F> {
+    pub fn from_evals(evals: Vec<F>) -> Self {
+        assert_power_of_two(&evals, "multilinear polynomial");
+        let num_vars = evals.len().ilog2() as usize;
+        Self { evals, num_vars }
+    }
+
+    pub fn from_fn(num_vars: usize, f: impl Fn(usize) -> F) -> Self {
+        Self::from_evals((0..1 << num_vars).map(f).collect())
+    }
+
+    pub fn evaluate(&self, point: &[F]) -> F {
+        assert_eq!(point.len(), self.num_vars);
+        eval_mle(point, &self.evals)
+    }
+
+    // Binds the first (most significant) variable to r
+    pub fn fix_variable(&mut self, r: F) {
+        assert!(self.num_vars > 0, "no variables left to fix");
+        self.evals = set_variable(&self.evals, r);
+        self.num_vars -= 1;
+    }
+
+    pub fn evals(&self) -> &[F] {
+        &self.evals
+    }
+
+    pub fn len(&self) -> usize {
+        self.evals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.evals.is_empty()
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+}
+
+// Evaluates an MLE given only its nonzero (index, value) entries, computing
+// each eq(point, index) directly instead of the full `chis` table
+pub fn eval_sparse_mle<F: PrimeField>(point: &[F], nonzeros: &[(usize, F)], num_vars: usize) -> F {
+    assert_eq!(point.len(), num_vars);
+    nonzeros
+        .iter()
+        .map(|&(index, value)| {
+            assert!(index < 1 << num_vars, "index {} out of range", index);
+            let eq: F = point
+                .iter()
+                .enumerate()
+                .map(|(j, &r)| {
+                    if (index >> (num_vars - 1 - j)) & 1 == 1 {
+                        r
+                    } else {
+                        F::ONE - r
+                    }
+                })
+                .product();
+            eq * value
+        })
+        .sum()
+}
+
+// Evaluates the MLE of f over the hypercube without building the table.
+// Index bits are big-endian: point[0] binds the most significant bit
+pub fn mle_eval_from_fn<F: PrimeField>(num_vars: usize, point: &[F], f: impl Fn(usize) -> F) -> F {
+    assert_eq!(point.len(), num_vars);
+    (0..1 << num_vars)
+        .map(|i| {
+            let eq: F = point
+                .iter()
+                .enumerate()
+                .map(|(j, &r)| {
+                    if (i >> (num_vars - 1 - j)) & 1 == 1 {
+                        r
+                    } else {
+                        F::ONE - r
+                    }
+                })
+                .product();
+            f(i) * eq
+        })
+        .sum()
+}
+
+#[test]
+fn test_mle_eval_from_fn() {
+    use ark_curve25519::Fr;
+
+    let point = vec![Fr::from(3), Fr::from(11), Fr::from(5)];
+    let identity: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
+    assert_eq!(
+        mle_eval_from_fn(3, &point, |i| Fr::from(i as u64)),
+        eval_mle(&point, &identity)
+    );
+    let squares: Vec<Fr> = (0..8).map(|i| Fr::from((i * i) as u64)).collect();
+    assert_eq!(
+        mle_eval_from_fn(3, &point, |i| Fr::from((i * i) as u64)),
+        eval_mle(&point, &squares)
+    );
+}
+
+#[test]
+fn test_eval_chis_threshold() {
+    use ark_curve25519::Fr;
+    use ark_std::UniformRand;
+
+    let mut rng = ark_std::test_rng();
+    for len in [PAR_THRESHOLD / 2, PAR_THRESHOLD * 2] {
+        let a: Vec<Fr> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+        let b: Vec<Fr> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+        let expected = a
+            .iter()
+            .zip(&b)
+            .fold(Fr::from(0), |acc, (&a, &b)| acc + a * b);
+        assert_eq!(eval_chis(&a, &b), expected);
+    }
+}
+
+#[test]
+fn test_multilinear_polynomial() {
+    use ark_curve25519::Fr;
+
+    let evals: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 * 7 + 2)).collect();
+    let point = vec![Fr::from(4), Fr::from(13), Fr::from(6)];
+    let mut poly = MultilinearPolynomial::from_evals(evals.clone());
+    assert_eq!(poly.num_vars(), 3);
+    assert_eq!(poly.len(), 8);
+    assert_eq!(poly.evaluate(&point), eval_mle(&point, &evals));
+    assert_eq!(
+        MultilinearPolynomial::from_fn(3, |i| Fr::from(i as u64 * 7 + 2)),
+        poly
+    );
+
+    poly.fix_variable(point[0]);
+    assert_eq!(poly.num_vars(), 2);
+    assert_eq!(poly.evals(), &set_variable(&evals, point[0])[..]);
+    assert_eq!(poly.evaluate(&point[1..]), eval_mle(&point, &evals));
+}
+
+#[test]
+#[should_panic(expected = "power-of-two")]
+fn test_multilinear_polynomial_bad_length() {
+    use ark_curve25519::Fr;
+
+    MultilinearPolynomial::from_evals(vec![Fr::from(1); 6]);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_chis_par() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for n in 1..=16 {
+        let point: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        assert_eq!(chis_par(&point), chis_serial(&point));
+    }
+}
+
+#[test]
+fn test_fix_variable_in_place() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for n in 1..=6 {
+        let mut mle: Vec<Fr> = (0..1 << n).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        let expected = set_variable(&mle, r);
+        fix_variable_in_place(&mut mle, r);
+        assert_eq!(mle, expected);
+    }
+}
+
+#[test]
+fn test_eval_sparse_mle() {
+    use ark_curve25519::Fr;
+    use ark_ff::{Field, Zero};
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let mut evals = vec![Fr::ZERO; 64];
+    for i in [0, 5, 17, 42, 63] {
+        evals[i] = Fr::rand(&mut rng);
+    }
+    let nonzeros: Vec<(usize, Fr)> = evals
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| !v.is_zero())
+        .map(|(i, &v)| (i, v))
+        .collect();
+    let point: Vec<Fr> = (0..6).map(|_| Fr::rand(&mut rng)).collect();
+    assert_eq!(
+        eval_sparse_mle(&point, &nonzeros, 6),
+        eval_mle(&point, &evals)
+    );
+    assert_eq!(eval_sparse_mle(&point, &[], 6), Fr::ZERO);
+}
+
+#[test]
+fn test_partial_eval() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let evals: Vec<Fr> = (0..32).map(|_| Fr::rand(&mut rng)).collect();
+    let point: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+    for k in 0..=5 {
+        let rest = partial_eval(&evals, &point[..k]);
+        assert_eq!(rest.len(), 32 >> k);
+        assert_eq!(eval_mle(&point[k..], &rest), eval_mle(&point, &evals));
+    }
+}
+
+#[test]
+fn test_eval_mle_batch() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let evals: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let points: Vec<Vec<Fr>> = (0..5)
+        .map(|_| (0..4).map(|_| Fr::rand(&mut rng)).collect())
+        .collect();
+    let expected: Vec<Fr> = points.iter().map(|p| eval_mle(p, &evals)).collect();
+    assert_eq!(eval_mle_batch(&points, &evals), expected);
+    assert!(eval_mle_batch(&[], &evals).is_empty());
+}
+
+#[test]
+fn test_eval_eq_prefix() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let a: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+    let b: Vec<Fr> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+    assert_eq!(eval_eq_prefix(&a, &b), eval_eq(&a[..3], &b));
+    assert_eq!(eval_eq_prefix(&b, &a), eval_eq(&b, &a[..3]));
+    assert_eq!(eval_eq_prefix(&a, &a), eval_eq(&a, &a));
+    assert_eq!(eval_eq_prefix(&a, &[]), Fr::from(1));
+}
+
+#[test]
+#[should_panic(expected = "set_variable: expected power-of-two table, got len 6")]
+fn set_variable_not_power_of_two() {
+    use ark_curve25519::Fr;
+    set_variable(&[Fr::from(1u64); 6], Fr::from(2u64));
+}
+
+#[test]
+#[should_panic(expected = "eval_mle: expected power-of-two table, got len 6")]
+fn eval_mle_not_power_of_two() {
+    use ark_curve25519::Fr;
+    eval_mle(&[Fr::from(3u64); 3], &[Fr::from(1u64); 6]);
+}
+
+#[test]
+fn bind_variable_orders() {
+    use ark_curve25519::Fr;
+
+    let mle: Vec<Fr> = (0..8u64).map(|i| Fr::from(i * i + 3)).collect();
+    let r = Fr::from(11u64);
+    assert_eq!(
+        bind_variable(&mle, r, BindOrder::Low),
+        set_variable(&mle, r)
+    );
+    let high = bind_variable(&mle, r, BindOrder::High);
+    let expected: Vec<Fr> = mle.chunks(2).map(|a| a[0] + r * (a[1] - a[0])).collect();
+    assert_eq!(high, expected);
+    assert_eq!(high, set_variable_second_half(&mle, r));
+    // Binding the last coordinate leaves the leading ones in place
+    let point = [Fr::from(2u64), Fr::from(5u64)];
+    assert_eq!(
+        eval_mle(&point, &high),
+        eval_mle(&[point[0], point[1], r], &mle)
+    );
+}
+
+#[test]
+fn chi_at_basis() {
+    use ark_curve25519::Fr;
+
+    let point = [Fr::from(3u64), Fr::from(8u64), Fr::from(21u64)];
+    let table = chis(&point);
+    for i in 0..8 {
+        let mut e_i = vec![Fr::from(0u64); 8];
+        e_i[i] = Fr::from(1u64);
+        assert_eq!(chi_at(&table, i), eval_mle(&point, &e_i));
+    }
+}
+
+#[test]
+fn eval_mle_streaming_matches() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for n in 0..=12 {
+        let point: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let evals: Vec<Fr> = (0..1 << n).map(|_| Fr::rand(&mut rng)).collect();
+        assert_eq!(eval_mle_streaming(&point, &evals), eval_mle(&point, &evals));
+    }
+}
+
+#[test]
+fn eq_table_entries() {
+    use ark_curve25519::Fr;
+
+    let point = [
+        Fr::from(4u64),
+        Fr::from(17u64),
+        Fr::from(2u64),
+        Fr::from(9u64),
+    ];
+    let table = eq_table(&point);
+    assert_eq!(table, chis(&point));
+    for (i, &entry) in table.iter().enumerate() {
+        assert_eq!(entry, eval_eq(&point, &index_to_bits(i, 4)));
+    }
 }
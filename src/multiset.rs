@@ -0,0 +1,95 @@
+use alloc::vec::Vec;
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    grandproduct::{GrandProductError, GrandProductProof},
+    multilinear::Opening,
+};
+
+// Maps each element to its root of the polynomial prod(x - tau)
+pub fn fingerprint<F: PrimeField>(set: &[F], tau: F) -> Vec<F> {
+    set.iter().map(|&x| x - tau).collect()
+}
+
+pub struct MultisetProof<F: PrimeField> {
+    a_proof: GrandProductProof<F>,
+    b_proof: GrandProductProof<F>,
+}
+
+// Proves set_a and set_b are equal as multisets by showing
+// prod(a_i - tau) == prod(b_i - tau). The caller must absorb commitments to
+// both sets before proving so tau is bound to them
+pub fn prove_equal<F: PrimeField>(
+    set_a: &[F],
+    set_b: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> MultisetProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(set_a.len(), set_b.len());
+    let tau = transcript.challenge_scalar(b"multiset_tau");
+    let a = fingerprint(set_a, tau);
+    let b = fingerprint(set_b, tau);
+    let (a_proof, _) = GrandProductProof::prove(&a, a.iter().product(), transcript);
+    let (b_proof, _) = GrandProductProof::prove(&b, b.iter().product(), transcript);
+    let proof = MultisetProof { a_proof, b_proof };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(start);
+    proof
+}
+
+impl<F: PrimeField> MultisetProof<F> {
+    // Replays `verify_equal` from the transcript state the prover started
+    // with; unequal sets fail here since their products differ
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("multiset", || verify_equal(self, &mut transcript).is_ok());
+    }
+}
+
+// Returns the (point, evaluation) openings of set_a and set_b, which the
+// caller must check against its commitments
+pub fn verify_equal<F: PrimeField>(
+    proof: &MultisetProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Opening<F>, Opening<F>), GrandProductError> {
+    let tau = transcript.challenge_scalar(b"multiset_tau");
+    // Each product is bound to the other's, which is the equality check
+    let (a_claim, a_point) = proof.a_proof.verify(proof.b_proof.claim(), transcript)?;
+    let (b_claim, b_point) = proof.b_proof.verify(proof.a_proof.claim(), transcript)?;
+    Ok(((a_point, a_claim + tau), (b_point, b_claim + tau)))
+}
+
+#[test]
+fn test_multiset_equal() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [5, 9, 9, 1].iter().map(|&x| Fr::from(x as u64)).collect();
+    let b: Vec<Fr> = [9, 1, 5, 9].iter().map(|&x| Fr::from(x as u64)).collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_equal(&a, &b, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((a_point, a_eval), (b_point, b_eval)) = verify_equal(&proof, &mut vtranscript).unwrap();
+    assert_eq!(a_eval, eval_mle(&a_point, &a));
+    assert_eq!(b_eval, eval_mle(&b_point, &b));
+}
+
+// Unequal sets, so the proof skips self-verify
+#[test]
+fn test_multiset_unequal() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [5, 9, 9, 1].iter().map(|&x| Fr::from(x as u64)).collect();
+    let b: Vec<Fr> = [9, 1, 5, 5].iter().map(|&x| Fr::from(x as u64)).collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_equal(&a, &b, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_equal(&proof, &mut vtranscript).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+}
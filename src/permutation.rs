@@ -0,0 +1,93 @@
+use alloc::vec::Vec;
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    grandproduct::{GrandProductError, GrandProductProof},
+    multiset::fingerprint,
+};
+
+// Proves sigma_col is a permutation of 0..n by showing prod(sigma_i - tau)
+// equals prod(i - tau). The caller must absorb a commitment to sigma_col
+// before proving so tau is bound to it
+pub fn prove_index_permutation<F: PrimeField>(
+    sigma_col: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> GrandProductProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    transcript.append_usize(b"permutation_size", sigma_col.len());
+    let tau = transcript.challenge_scalar(b"permutation_tau");
+    let fingerprints = fingerprint(sigma_col, tau);
+    let product = fingerprints.iter().product();
+    let proof = GrandProductProof::prove(&fingerprints, product, transcript).0;
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    self_verify(sigma_col.len(), &proof, start);
+    proof
+}
+
+// Replays `verify_index_permutation` from the transcript state the prover
+// started with; a sigma that is not a permutation fails here since its
+// product differs from the identity's
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+fn self_verify<F: PrimeField>(
+    n: usize,
+    proof: &GrandProductProof<F>,
+    mut transcript: impl ProtocolTranscript<F>,
+) {
+    crate::selfverify::check("permutation", || {
+        verify_index_permutation(n, proof, &mut transcript).is_ok()
+    });
+}
+
+// Returns the point and the claimed evaluation of sigma_col there, which
+// the caller must check against its commitment
+pub fn verify_index_permutation<F: PrimeField>(
+    n: usize,
+    proof: &GrandProductProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<(Vec<F>, F), GrandProductError> {
+    transcript.append_usize(b"permutation_size", n);
+    let tau = transcript.challenge_scalar(b"permutation_tau");
+    let identity: Vec<F> = (0..n).map(|i| F::from(i as u64)).collect();
+    let identity: F = fingerprint(&identity, tau).iter().product();
+    let (final_claim, z) = proof.verify(identity, transcript)?;
+    // The MLE of (sigma - tau) is sigma's MLE shifted by tau
+    Ok((z, final_claim + tau))
+}
+
+#[test]
+fn test_index_permutation() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let sigma: Vec<Fr> = [3, 0, 7, 5, 1, 2, 6, 4]
+        .iter()
+        .map(|&i| Fr::from(i as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_index_permutation(&sigma, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (z, eval) = verify_index_permutation(sigma.len(), &proof, &mut vtranscript).unwrap();
+    assert_eq!(eval, eval_mle(&z, &sigma));
+}
+
+// Not a permutation, so the proof skips self-verify
+#[test]
+fn test_index_permutation_not_bijective() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let sigma: Vec<Fr> = [3, 0, 7, 5, 1, 1, 6, 4]
+        .iter()
+        .map(|&i| Fr::from(i as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_index_permutation(&sigma, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_index_permutation(sigma.len(), &proof, &mut vtranscript).err(),
+        Some(GrandProductError::ClaimMismatch)
+    );
+}
@@ -0,0 +1,189 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    multilinear::{chis, eval_eq, eval_mle, Opening},
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+// Table of y -> eq(r, y + 1), so that sum_y shift_table(r)[y] * v[y] is the
+// MLE of v shifted down by one index (with a zero at index 0) evaluated at r
+fn shift_table<F: PrimeField>(r: &[F]) -> Vec<F> {
+    let mut table = chis(r);
+    table.remove(0);
+    table.push(F::ZERO);
+    table
+}
+
+// The openings of input at r, prefix at r, and prefix at the shift
+// sumcheck's point
+pub type PrefixSumOpenings<F> = (Opening<F>, Opening<F>, Opening<F>);
+
+pub struct PrefixSumProof<F: PrimeField> {
+    difference_proof: SumcheckProof<F>,
+    shift_proof: SumcheckProof<F>,
+    input_eval: F,
+    prefix_eval: F,
+    shifted_eval: F,
+    shift_prefix_eval: F,
+}
+
+// Proves prefix_i == prefix_{i-1} + input_i with prefix_0 == input_0. The
+// caller must absorb commitments to both vectors before proving
+pub fn prove<F: PrimeField>(
+    input: &[F],
+    prefix: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> PrefixSumProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(input.len(), prefix.len());
+    let num_vars = input.len().ilog2() as usize;
+    let rz = transcript.challenge_scalars(b"prefix_sum_rz", num_vars);
+
+    let shifted: Vec<F> = core::iter::once(F::ZERO)
+        .chain(prefix[..prefix.len() - 1].iter().cloned())
+        .collect();
+    let difference: Vec<F> = (0..input.len())
+        .map(|i| prefix[i] - shifted[i] - input[i])
+        .collect();
+    let difference_proof = SumcheckProof::prove(F::ZERO, vec![chis(&rz), difference], transcript);
+
+    let r = &difference_proof.rands;
+    let (input_eval, prefix_eval) = (eval_mle(r, input), eval_mle(r, prefix));
+    let shifted_eval = eval_mle(r, &shifted);
+    transcript.append_scalar(b"prefix_sum_eval", &input_eval);
+    transcript.append_scalar(b"prefix_sum_eval", &prefix_eval);
+    transcript.append_scalar(b"prefix_sum_eval", &shifted_eval);
+
+    let shift_proof = SumcheckProof::prove(
+        shifted_eval,
+        vec![shift_table(r), prefix.to_vec()],
+        transcript,
+    );
+    let shift_prefix_eval = shift_proof.final_terms[1];
+    transcript.append_scalar(b"prefix_sum_eval", &shift_prefix_eval);
+    let proof = PrefixSumProof {
+        difference_proof,
+        shift_proof,
+        input_eval,
+        prefix_eval,
+        shifted_eval,
+        shift_prefix_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(input, prefix, start);
+    proof
+}
+
+impl<F: PrimeField> PrefixSumProof<F> {
+    // Replays `verify` from the transcript state the prover started with and
+    // checks the openings it returns against input and prefix
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, input: &[F], prefix: &[F], mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("prefix sum", || {
+            matches!(
+                verify(input.len(), self, &mut transcript),
+                Ok(((r, input_eval), (_, prefix_eval), (r2, shift_prefix_eval)))
+                    if eval_mle(&r, input) == input_eval
+                        && eval_mle(&r, prefix) == prefix_eval
+                        && eval_mle(&r2, prefix) == shift_prefix_eval
+            )
+        });
+    }
+}
+
+// Returns the openings of input at r, prefix at r, and prefix at the shift
+// sumcheck's point, which the caller must check against its commitments
+pub fn verify<F: PrimeField>(
+    len: usize,
+    proof: &PrefixSumProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<PrefixSumOpenings<F>, SumcheckError> {
+    let num_vars = len.ilog2() as usize;
+    let rz = transcript.challenge_scalars(b"prefix_sum_rz", num_vars);
+    // The eq-weighted difference must sum to zero
+    if proof.difference_proof.claim != F::ZERO {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    let (r, expected) = proof
+        .difference_proof
+        .verify_shape(num_vars, 2, transcript)?;
+    let difference = proof.prefix_eval - proof.shifted_eval - proof.input_eval;
+    if expected != eval_eq(&rz, &r) * difference {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"prefix_sum_eval", &proof.input_eval);
+    transcript.append_scalar(b"prefix_sum_eval", &proof.prefix_eval);
+    transcript.append_scalar(b"prefix_sum_eval", &proof.shifted_eval);
+
+    if proof.shift_proof.claim != proof.shifted_eval {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    let (r2, expected) = proof.shift_proof.verify_shape(num_vars, 2, transcript)?;
+    let shift = eval_mle(&r2, &shift_table(&r));
+    if expected != shift * proof.shift_prefix_eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"prefix_sum_eval", &proof.shift_prefix_eval);
+    Ok((
+        (r.clone(), proof.input_eval),
+        (r, proof.prefix_eval),
+        (r2, proof.shift_prefix_eval),
+    ))
+}
+
+#[test]
+fn test_prefix_sum() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let input: Vec<Fr> = [3, 1, 4, 1, 5, 9, 2, 6]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let prefix: Vec<Fr> = input
+        .iter()
+        .scan(Fr::from(0), |acc, &x| {
+            *acc += x;
+            Some(*acc)
+        })
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&input, &prefix, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let ((r, input_eval), (_, prefix_eval), (r2, shift_eval)) =
+        verify(input.len(), &proof, &mut vtranscript).unwrap();
+    assert_eq!(input_eval, eval_mle(&r, &input));
+    assert_eq!(prefix_eval, eval_mle(&r, &prefix));
+    assert_eq!(shift_eval, eval_mle(&r2, &prefix));
+}
+
+// A wrong prefix, so the proof skips self-verify
+#[test]
+fn test_prefix_sum_off_by_one() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let input: Vec<Fr> = [3, 1, 4, 1, 5, 9, 2, 6]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    // Exclusive rather than inclusive running sum
+    let prefix: Vec<Fr> = input
+        .iter()
+        .scan(Fr::from(0), |acc, &x| {
+            let prev = *acc;
+            *acc += x;
+            Some(prev)
+        })
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove(&input, &prefix, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(input.len(), &proof, &mut vtranscript),
+        Err(SumcheckError::FinalEvalMismatch)
+    );
+}
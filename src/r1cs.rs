@@ -0,0 +1,303 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    multilinear::{chis, eval_eq, eval_sparse_mle, Opening},
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+// A size x size matrix by its nonzero (row, col, value) entries. size must
+// be a power of two
+pub struct SparseMatrix<F: PrimeField> {
+    pub size: usize,
+    pub entries: Vec<(usize, usize, F)>,
+}
+
+impl<F: PrimeField> SparseMatrix<F> {
+    pub fn mul_vec(&self, z: &[F]) -> Vec<F> {
+        let mut out = vec![F::ZERO; self.size];
+        for &(row, col, value) in &self.entries {
+            out[row] += value * z[col];
+        }
+        out
+    }
+
+    // The row variables come first, then the column variables
+    pub fn evaluate(&self, point: &[F]) -> F {
+        let nonzeros: Vec<(usize, F)> = self
+            .entries
+            .iter()
+            .map(|&(row, col, value)| (row * self.size + col, value))
+            .collect();
+        eval_sparse_mle(point, &nonzeros, 2 * self.size.ilog2() as usize)
+    }
+
+    // The table of M(rx, y) over y
+    fn bind_rows(&self, rx_chis: &[F]) -> Vec<F> {
+        let mut out = vec![F::ZERO; self.size];
+        for &(row, col, value) in &self.entries {
+            out[col] += rx_chis[row] * value;
+        }
+        out
+    }
+}
+
+pub struct R1CSProof<F: PrimeField> {
+    outer: SumcheckProof<F>,
+    inner: SumcheckProof<F>,
+}
+
+// Proves Az o Bz = Cz with Spartan's two sumchecks. The outer one shows
+// sum_x eq(tau, x) * (Az(x) * Bz(x) - Cz(x)) = 0, leaving claims on Az, Bz
+// and Cz at rx; the inner one folds them with random weights into
+// sum_y (ra * A + rb * B + rc * C)(rx, y) * z(y). The caller must absorb its
+// commitment to z first
+pub fn prove<F: PrimeField>(
+    a: &SparseMatrix<F>,
+    b: &SparseMatrix<F>,
+    c: &SparseMatrix<F>,
+    z: &[F],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> R1CSProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let start = transcript.clone();
+    assert_eq!(z.len(), a.size);
+    let num_vars = a.size.ilog2() as usize;
+    let tau = transcript.challenge_scalars(b"r1cs_tau", num_vars);
+    let (az, bz, cz) = (a.mul_vec(z), b.mul_vec(z), c.mul_vec(z));
+    let outer = SumcheckProof::prove_with(
+        F::ZERO,
+        vec![chis(&tau), az, bz, cz],
+        3,
+        |t: &[F]| t[0] * (t[1] * t[2] - t[3]),
+        transcript,
+    );
+    let (az_eval, bz_eval, cz_eval) = (
+        outer.final_terms[1],
+        outer.final_terms[2],
+        outer.final_terms[3],
+    );
+
+    // The weights must not be known when the three evaluations are chosen
+    transcript.append_points(b"r1cs_outer_evals", &outer.final_terms[1..]);
+    let [ra, rb, rc] = [b"r1cs_ra", b"r1cs_rb", b"r1cs_rc"].map(|l| transcript.challenge_scalar(l));
+    let claim = ra * az_eval + rb * bz_eval + rc * cz_eval;
+    let rx_chis = chis(&outer.rands);
+    let combined: Vec<F> = a
+        .bind_rows(&rx_chis)
+        .into_iter()
+        .zip(b.bind_rows(&rx_chis))
+        .zip(c.bind_rows(&rx_chis))
+        .map(|((a, b), c)| ra * a + rb * b + rc * c)
+        .collect();
+    let inner = SumcheckProof::prove(claim, vec![combined, z.to_vec()], transcript);
+    let proof = R1CSProof { outer, inner };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify([a, b, c], z, start);
+    proof
+}
+
+impl<F: PrimeField> R1CSProof<F> {
+    // Replays `verify` from the transcript state the prover started with and
+    // checks the opening of z it returns
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(
+        &self,
+        [a, b, c]: [&SparseMatrix<F>; 3],
+        z: &[F],
+        mut transcript: impl ProtocolTranscript<F>,
+    ) {
+        crate::selfverify::check("r1cs", || {
+            matches!(
+                verify(a, b, c, self, &mut transcript),
+                Ok((ry, z_eval)) if crate::multilinear::eval_mle(&ry, z) == z_eval
+            )
+        });
+    }
+}
+
+// Returns the opening of z at ry, which the caller must check against its
+// commitment. The verifier evaluates the matrices from their entries itself;
+// there is no sparse polynomial commitment in the crate to open them with
+pub fn verify<F: PrimeField>(
+    a: &SparseMatrix<F>,
+    b: &SparseMatrix<F>,
+    c: &SparseMatrix<F>,
+    proof: &R1CSProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<Opening<F>, SumcheckError> {
+    let num_vars = a.size.ilog2() as usize;
+    let tau = transcript.challenge_scalars(b"r1cs_tau", num_vars);
+    if proof.outer.claim != F::ZERO {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    // eq * (Az * Bz - Cz) over the row variables
+    let (rx, expected) = proof.outer.verify_shape(num_vars, 3, transcript)?;
+    let terms = &proof.outer.final_terms;
+    if terms.len() != 4 {
+        return Err(SumcheckError::FinalTermCountMismatch {
+            got: terms.len(),
+            expected: 4,
+        });
+    }
+    if expected != eval_eq(&tau, &rx) * (terms[1] * terms[2] - terms[3]) {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+
+    transcript.append_points(b"r1cs_outer_evals", &terms[1..]);
+    let [ra, rb, rc] = [b"r1cs_ra", b"r1cs_rb", b"r1cs_rc"].map(|l| transcript.challenge_scalar(l));
+    let claim = ra * terms[1] + rb * terms[2] + rc * terms[3];
+    if proof.inner.claim != claim {
+        return Err(SumcheckError::UnexpectedClaim);
+    }
+    // The combined matrix row times z over the column variables
+    let (ry, expected) = proof.inner.verify_shape(num_vars, 2, transcript)?;
+    if proof.inner.final_terms.len() != 2 {
+        return Err(SumcheckError::FinalTermCountMismatch {
+            got: proof.inner.final_terms.len(),
+            expected: 2,
+        });
+    }
+    let point = [&rx[..], &ry[..]].concat();
+    let m_eval = ra * a.evaluate(&point) + rb * b.evaluate(&point) + rc * c.evaluate(&point);
+    let z_eval = proof.inner.final_terms[1];
+    if expected != m_eval * z_eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    Ok((ry, z_eval))
+}
+
+#[cfg(test)]
+fn instance() -> (
+    SparseMatrix<ark_curve25519::Fr>,
+    SparseMatrix<ark_curve25519::Fr>,
+    SparseMatrix<ark_curve25519::Fr>,
+) {
+    use ark_curve25519::Fr;
+
+    // z = (1, x, y, out) with x * y = out and (x + 1) * 1 = y
+    let one = Fr::from(1u64);
+    let a = SparseMatrix {
+        size: 4,
+        entries: vec![(0, 1, one), (1, 1, one), (1, 0, one)],
+    };
+    let b = SparseMatrix {
+        size: 4,
+        entries: vec![(0, 2, one), (1, 0, one)],
+    };
+    let c = SparseMatrix {
+        size: 4,
+        entries: vec![(0, 3, one), (1, 2, one)],
+    };
+    (a, b, c)
+}
+
+#[test]
+fn test_r1cs() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let (a, b, c) = instance();
+    let z: Vec<Fr> = [1u64, 3, 4, 12].map(Fr::from).to_vec();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, &c, &z, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (ry, z_eval) = verify(&a, &b, &c, &proof, &mut vtranscript).unwrap();
+    assert_eq!(z_eval, crate::multilinear::eval_mle(&ry, &z));
+}
+
+// An unsatisfying witness, so its proof skips self-verify
+#[test]
+fn test_r1cs_unsatisfied() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let (a, b, c) = instance();
+    let z: Vec<Fr> = [1u64, 3, 4, 13].map(Fr::from).to_vec();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove(&a, &b, &c, &z, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(verify(&a, &b, &c, &proof, &mut vtranscript).is_err());
+}
+
+// Evaluations picked after seeing the weights, the way a prover for the
+// unsatisfying witness would if they were not bound first: Cz is replaced
+// by Az o Bz in the outer sumcheck, then the three claims are solved to hit
+// both the outer relation and the honest inner sum
+#[test]
+fn test_r1cs_forged_evals() {
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+    use merlin::Transcript;
+
+    let (a, b, c) = instance();
+    let z: Vec<Fr> = [1u64, 3, 4, 13].map(Fr::from).to_vec();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let tau: Vec<Fr> = transcript.challenge_scalars(b"r1cs_tau", 2);
+    let (az, bz) = (a.mul_vec(&z), b.mul_vec(&z));
+    let azbz = az.iter().zip(&bz).map(|(x, y)| x * y).collect();
+    let mut outer = SumcheckProof::prove_with(
+        Fr::from(0u64),
+        vec![chis(&tau), az, bz, azbz],
+        3,
+        |t: &[Fr]| t[0] * (t[1] * t[2] - t[3]),
+        &mut transcript,
+    );
+    let rx_chis = chis(&outer.rands);
+    let [ra, rb, rc]: [Fr; 3] =
+        [b"r1cs_ra", b"r1cs_rb", b"r1cs_rc"].map(|l| transcript.challenge_scalar(l));
+    let rows = [&a, &b, &c].map(|m| m.bind_rows(&rx_chis));
+    let combined: Vec<Fr> = (0..4)
+        .map(|y| ra * rows[0][y] + rb * rows[1][y] + rc * rows[2][y])
+        .collect();
+    let claim: Fr = combined.iter().zip(&z).map(|(m, z)| m * z).sum();
+    let t = &outer.final_terms;
+    let k = t[1] * t[2] - t[3];
+    let t1 = t[1];
+    let t2 = (claim - ra * t1 + rc * k) * (rb + rc * t1).inverse().unwrap();
+    outer.final_terms = vec![t[0], t1, t2, t1 * t2 - k];
+    let inner = SumcheckProof::prove(claim, vec![combined, z.clone()], &mut transcript);
+
+    let proof = R1CSProof { outer, inner };
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify(&a, &b, &c, &proof, &mut vtranscript),
+        Err(SumcheckError::UnexpectedClaim)
+    );
+}
+
+#[test]
+fn test_r1cs_malformed() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let (a, b, c) = instance();
+    let z: Vec<Fr> = [1u64, 3, 4, 12].map(Fr::from).to_vec();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove(&a, &b, &c, &z, &mut transcript);
+    let verify =
+        |proof: &R1CSProof<Fr>| verify(&a, &b, &c, proof, &mut Transcript::new(b"test_transcript"));
+    let mut short = R1CSProof {
+        outer: proof.outer.clone(),
+        inner: proof.inner.clone(),
+    };
+    short.inner.final_terms.pop();
+    assert_eq!(
+        verify(&short).err(),
+        Some(SumcheckError::FinalTermCountMismatch {
+            got: 1,
+            expected: 2
+        })
+    );
+    let mut truncated = proof;
+    truncated.outer.polynomials.pop();
+    truncated.outer.rounds -= 1;
+    assert_eq!(
+        verify(&truncated).err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 1,
+            expected: 2
+        })
+    );
+}
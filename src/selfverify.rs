@@ -0,0 +1,48 @@
+// With debug_self_verify on, debug builds replay the verifier on every
+// freshly produced proof, from the transcript state the prover started
+// with, so a prover/verifier absorb mismatch fails where it is introduced
+// instead of at some later verify. Otherwise the checks compile out
+
+// Set by `skipped` so tests of dishonest provers can produce proofs that
+// are meant to be rejected
+#[cfg(test)]
+std::thread_local! {
+    static SKIP: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+}
+
+// Panics naming the protocol unless verifies() accepts the proof
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+pub(crate) fn check(name: &str, verifies: impl FnOnce() -> bool) {
+    #[cfg(test)]
+    if SKIP.with(|skip| skip.get()) {
+        return;
+    }
+    assert!(
+        verifies(),
+        "{} self-verify failed: freshly produced proof does not verify",
+        name
+    );
+}
+
+// Runs f with self-verify off
+#[cfg(test)]
+pub(crate) fn skipped<T>(f: impl FnOnce() -> T) -> T {
+    SKIP.with(|skip| skip.set(true));
+    let result = f();
+    SKIP.with(|skip| skip.set(false));
+    result
+}
+
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+#[test]
+#[should_panic(expected = "demo self-verify failed")]
+fn test_check_fires() {
+    check("demo", || false);
+}
+
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+#[test]
+fn test_skipped() {
+    skipped(|| check("demo", || false));
+    check("demo", || true);
+}
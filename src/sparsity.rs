@@ -0,0 +1,233 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+
+use crate::{
+    fiatshamir::ProtocolTranscript,
+    field_utils::batch_inverse,
+    multilinear::{chis, eval_eq, Opening},
+    sumcheck::{SumcheckError, SumcheckProof},
+};
+
+pub struct HammingWeightProof<F: PrimeField> {
+    zero_proof: SumcheckProof<F>,
+    sum_proof: SumcheckProof<F>,
+    a_eval: F,
+    b_eval: F,
+    c_eval: F,
+    b_sum_eval: F,
+}
+
+// The zero check's summand: booleanity b * (1 - b), the tie a * (1 - b) and
+// the inverse witness a * c - b, batched with gamma and weighted by eq
+fn hamming_comb<F: PrimeField>(eq: F, a: F, b: F, c: F, gamma: F) -> F {
+    eq * ((F::ONE - b) * (b + gamma * a) + gamma.square() * (a * c - b))
+}
+
+// Proves a has exactly k nonzero entries using the indicator b (b_i = 1 iff
+// a_i != 0) and c, the inverse of a where it is nonzero and zero elsewhere.
+// a * (1 - b) == 0 gives a_i != 0 => b_i = 1 and a * c == b gives the
+// converse; with booleanity they go into one eq-weighted zero check, and a
+// linear sumcheck shows sum b_i == k. The caller must absorb commitments to
+// a, b and c first
+pub fn prove_hamming_weight<F: PrimeField>(
+    a: &[F],
+    k: usize,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> HammingWeightProof<F> {
+    let b: Vec<F> = a
+        .iter()
+        .map(|x| if x.is_zero() { F::ZERO } else { F::ONE })
+        .collect();
+    let nonzero: Vec<F> = a.iter().filter(|x| !x.is_zero()).cloned().collect();
+    let mut inverses = batch_inverse(&nonzero).into_iter();
+    let c: Vec<F> = a
+        .iter()
+        .map(|x| {
+            if x.is_zero() {
+                F::ZERO
+            } else {
+                inverses.next().unwrap()
+            }
+        })
+        .collect();
+    prove_with_witness(a, b, c, k, transcript)
+}
+
+fn prove_with_witness<F: PrimeField>(
+    a: &[F],
+    b: Vec<F>,
+    c: Vec<F>,
+    k: usize,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> HammingWeightProof<F> {
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    let (start, tables) = (transcript.clone(), [a.to_vec(), b.clone(), c.clone()]);
+    transcript.append_usize(b"hamming_weight", k);
+    let r = transcript.challenge_scalars(b"hamming_weight_r", a.len().ilog2() as usize);
+    let gamma = transcript.challenge_scalar(b"hamming_weight_gamma");
+    let zero_proof = SumcheckProof::prove_with(
+        F::ZERO,
+        vec![chis(&r), a.to_vec(), b.clone(), c],
+        3,
+        |v| hamming_comb(v[0], v[1], v[2], v[3], gamma),
+        transcript,
+    );
+    let [_, a_eval, b_eval, c_eval] = zero_proof.final_terms[..] else {
+        unreachable!()
+    };
+    for eval in [a_eval, b_eval, c_eval] {
+        transcript.append_scalar(b"hamming_weight_eval", &eval);
+    }
+
+    let sum_proof = SumcheckProof::prove(F::from(k as u64), vec![b], transcript);
+    let b_sum_eval = sum_proof.final_terms[0];
+    transcript.append_scalar(b"hamming_weight_eval", &b_sum_eval);
+    let proof = HammingWeightProof {
+        zero_proof,
+        sum_proof,
+        a_eval,
+        b_eval,
+        c_eval,
+        b_sum_eval,
+    };
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    proof.self_verify(tables, k, start);
+    proof
+}
+
+impl<F: PrimeField> HammingWeightProof<F> {
+    // Replays `verify_hamming_weight` from the transcript state the prover
+    // started with and checks the openings it returns against a, b and c
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(
+        &self,
+        [a, b, c]: [Vec<F>; 3],
+        k: usize,
+        mut transcript: impl ProtocolTranscript<F>,
+    ) {
+        use crate::multilinear::eval_mle;
+
+        crate::selfverify::check("hamming weight", || {
+            verify_hamming_weight(a.len(), k, self, &mut transcript).is_ok_and(|openings| {
+                openings
+                    .iter()
+                    .zip([&a, &b, &c, &b])
+                    .all(|((point, eval), table)| eval_mle(point, table) == *eval)
+            })
+        });
+    }
+}
+
+// Returns the openings of a, b and c at the zero check's point and of b at
+// the sum check's point, which the caller must check against its
+// commitments
+pub fn verify_hamming_weight<F: PrimeField>(
+    len: usize,
+    k: usize,
+    proof: &HammingWeightProof<F>,
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> Result<[Opening<F>; 4], SumcheckError> {
+    transcript.append_usize(b"hamming_weight", k);
+    let r = transcript.challenge_scalars(b"hamming_weight_r", len.ilog2() as usize);
+    let gamma = transcript.challenge_scalar(b"hamming_weight_gamma");
+    let (point, expected) = proof.zero_proof.verify_claim(F::ZERO, transcript)?;
+    let (a_eval, b_eval, c_eval) = (proof.a_eval, proof.b_eval, proof.c_eval);
+    if expected != hamming_comb(eval_eq(&r, &point), a_eval, b_eval, c_eval, gamma) {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    for eval in [a_eval, b_eval, c_eval] {
+        transcript.append_scalar(b"hamming_weight_eval", &eval);
+    }
+
+    let (sum_point, expected) = proof
+        .sum_proof
+        .verify_claim(F::from(k as u64), transcript)?;
+    if expected != proof.b_sum_eval {
+        return Err(SumcheckError::FinalEvalMismatch);
+    }
+    transcript.append_scalar(b"hamming_weight_eval", &proof.b_sum_eval);
+    Ok([
+        (point.clone(), a_eval),
+        (point.clone(), b_eval),
+        (point, c_eval),
+        (sum_point, proof.b_sum_eval),
+    ])
+}
+
+#[test]
+fn test_hamming_weight() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [0, 4, 0, 0, 7, 1, 0, 9]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let b: Vec<Fr> = [0, 1, 0, 0, 1, 1, 0, 1]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let c: Vec<Fr> = a
+        .iter()
+        .map(|x| x.inverse().unwrap_or(Fr::from(0)))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = prove_hamming_weight(&a, 4, &mut transcript);
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let [(r, a_eval), (_, b_eval), (_, c_eval), (r2, b_sum_eval)] =
+        verify_hamming_weight(a.len(), 4, &proof, &mut vtranscript).unwrap();
+    assert_eq!(a_eval, eval_mle(&r, &a));
+    assert_eq!(b_eval, eval_mle(&r, &b));
+    assert_eq!(c_eval, eval_mle(&r, &c));
+    assert_eq!(b_sum_eval, eval_mle(&r2, &b));
+}
+
+// A dishonest prover, so its proof skips self-verify
+#[test]
+fn test_wrong_hamming_weight() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [0, 4, 0, 0, 7, 1, 0, 9]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_hamming_weight(&a, 3, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_hamming_weight(a.len(), 3, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::FinalEvalMismatch)
+    );
+}
+
+// Overclaims by marking a zero entry of a as nonzero, which only the
+// inverse witness catches, so the proof skips self-verify
+#[test]
+fn test_overclaimed_hamming_weight() {
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = [0, 4, 0, 0, 7, 1, 0, 9]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let b: Vec<Fr> = [1, 1, 0, 0, 1, 1, 0, 1]
+        .iter()
+        .map(|&x| Fr::from(x as u64))
+        .collect();
+    let c: Vec<Fr> = a
+        .iter()
+        .map(|x| x.inverse().unwrap_or(Fr::from(0)))
+        .collect();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = crate::selfverify::skipped(|| prove_with_witness(&a, b, c, 5, &mut transcript));
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        verify_hamming_weight(a.len(), 5, &proof, &mut vtranscript).err(),
+        Some(SumcheckError::FinalEvalMismatch)
+    );
+}
@@ -1,116 +1,986 @@
-use ark_ff::{BigInteger, PrimeField};
-use merlin::Transcript;
+use alloc::{vec, vec::Vec};
+use ark_ff::{Field, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 use crate::{
-    fiatshamir::ProtocolTranscript,
-    multilinear::{chis, eval_chis, set_variable},
-    univariate::eval_ule,
+    fiatshamir::{ProtocolTranscript, Provable},
+    multilinear::{
+        assert_power_of_two, chis, eval_eq, fix_variable_in_place, pad_next_power_of_two, Opening,
+    },
+    univariate::{eval_ule, eval_ule_field, BarycentricDomain},
 };
 
-fn derive_points<F: PrimeField>(mles: &[Vec<F>], last_claim: F) -> Vec<F> {
-    let degree = mles.len() + 1;
-    let mle_len = mles[0].len();
-    let mle_half = mle_len / 2;
-    let mut points = vec![F::ZERO; degree];
-    for i in 0..mle_half {
-        for j in 0..degree {
-            if j == 1 {
-                points[j] = last_claim - points[0];
-            } else {
-                let t = F::from(j as u64);
-                let mut product = F::ONE;
-                for k in 0..mles.len() {
-                    product *= mles[k][i] * (F::ONE - t) + mles[k][i + mle_half] * t;
-                }
-                points[j] += product
-            }
-        }
+// Round polynomial at each node of domain, where each hypercube cell
+// contributes comb of the MLE values there. domain starts 0, 1 and its
+// length is the degree + 1 points sent; the point at 1 is never summed but
+// set from the claim at the end
+fn derive_points<F: Field>(
+    mles: &[Vec<F>],
+    domain: &[F],
+    comb: &(impl Fn(&[F]) -> F + Sync),
+    last_claim: F,
+) -> Vec<F> {
+    debug_assert!(domain[0].is_zero() && domain[1].is_one());
+    #[cfg(feature = "parallel")]
+    if mles[0].len() / 2 >= crate::multilinear::PAR_THRESHOLD {
+        return derive_points_par(mles, domain, comb, last_claim);
+    }
+    derive_points_serial(mles, domain, comb, last_claim)
+}
+
+// The nodes 0..=degree the round polynomials are sent at
+fn round_domain<F: Field>(degree: usize) -> Vec<F> {
+    (0..=degree).map(|j| F::from(j as u64)).collect()
+}
+
+fn derive_points_serial<F: Field>(
+    mles: &[Vec<F>],
+    domain: &[F],
+    comb: &impl Fn(&[F]) -> F,
+    last_claim: F,
+) -> Vec<F> {
+    let mut points = vec![F::ZERO; domain.len()];
+    let mut values = vec![F::ZERO; mles.len()];
+    let mut diffs = vec![F::ZERO; mles.len()];
+    for i in 0..mles[0].len() / 2 {
+        add_cell(mles, i, domain, comb, &mut points, &mut values, &mut diffs);
     }
+    points[1] = last_claim - points[0];
     points
 }
 
-#[derive(Clone)]
-pub struct SumcheckProof<F: PrimeField + From<i32>> {
+// Splits the hypercube half across threads, each accumulating its own
+// points, then sums the accumulators
+#[cfg(feature = "parallel")]
+fn derive_points_par<F: Field>(
+    mles: &[Vec<F>],
+    domain: &[F],
+    comb: &(impl Fn(&[F]) -> F + Sync),
+    last_claim: F,
+) -> Vec<F> {
+    use rayon::prelude::*;
+    let scratch = || vec![F::ZERO; mles.len()];
+    let mut points = (0..mles[0].len() / 2)
+        .into_par_iter()
+        .fold(
+            || (vec![F::ZERO; domain.len()], scratch(), scratch()),
+            |(mut points, mut values, mut diffs), i| {
+                add_cell(mles, i, domain, comb, &mut points, &mut values, &mut diffs);
+                (points, values, diffs)
+            },
+        )
+        .map(|(points, _, _)| points)
+        .reduce(
+            || vec![F::ZERO; domain.len()],
+            |a, b| a.iter().zip(&b).map(|(&a, &b)| a + b).collect(),
+        );
+    points[1] = last_claim - points[0];
+    points
+}
+
+// Adds cell i's contribution to point 0 and the points from 2 on; the
+// caller fills in point 1 from the claim. Each MLE is lo + t * (hi - lo) on
+// the line through the cell, so the difference is taken once per cell
+fn add_cell<F: Field>(
+    mles: &[Vec<F>],
+    i: usize,
+    domain: &[F],
+    comb: &impl Fn(&[F]) -> F,
+    points: &mut [F],
+    values: &mut [F],
+    diffs: &mut [F],
+) {
+    let mle_half = mles[0].len() / 2;
+    for ((value, diff), mle) in values.iter_mut().zip(diffs.iter_mut()).zip(mles) {
+        *value = mle[i];
+        *diff = mle[i + mle_half] - mle[i];
+    }
+    points[0] += comb(values);
+    for (point, &t) in points.iter_mut().zip(domain).skip(2) {
+        for ((value, &diff), mle) in values.iter_mut().zip(diffs.iter()).zip(mles) {
+            *value = mle[i] + t * diff;
+        }
+        *point += comb(values);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SumcheckError {
+    UnexpectedClaim,
+    ClaimMismatch {
+        round: usize,
+    },
+    FinalEvalMismatch,
+    RoundCountMismatch {
+        got: usize,
+        expected: usize,
+    },
+    DegreeMismatch {
+        round: usize,
+        got: usize,
+        expected: usize,
+    },
+    // Round polynomials need at least the two points summed each round
+    InvalidDegree {
+        got: usize,
+    },
+    UnexpectedDegree {
+        got: usize,
+        expected: usize,
+    },
+    FinalTermCountMismatch {
+        got: usize,
+        expected: usize,
+    },
+    // A challenge of 1 among a padded table's leading coordinates zeroes the
+    // extended table there, so the proof says nothing about the original
+    ZeroPaddingFactor {
+        table: usize,
+    },
+    // The bytes are not a canonical encoding of a proof
+    Undecodable,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SumcheckProof<F: PrimeField> {
     pub polynomials: Vec<Vec<F>>,
     pub rands: Vec<F>,
     pub final_terms: Vec<F>,
     pub degree: usize,
     pub rounds: usize,
     pub claim: F,
+    // Round polynomials omit their evaluation at 1, which the verifier
+    // recovers as the previous claim minus the evaluation at 0
+    pub compressed: bool,
 }
 
-impl<F: PrimeField + From<i32>> SumcheckProof<F> {
-    pub fn prove(
+impl<F: PrimeField> SumcheckProof<F> {
+    pub fn prove(claim: F, mles: Vec<Vec<F>>, transcript: &mut impl ProtocolTranscript<F>) -> Self {
+        for mle in &mles {
+            assert_power_of_two(mle, "sumcheck");
+        }
+        let degree = mles.len();
+        Self::prove_with(
+            claim,
+            mles,
+            degree,
+            |values| values.iter().product(),
+            transcript,
+        )
+    }
+
+    // Like `prove` for tables of different lengths: each is zero-extended to
+    // the longest, so the claim is over the extended tables. A table of
+    // length n then has its own variables last, and `unpadded_openings`
+    // recovers openings of the original tables from the final terms. Returns
+    // the original lengths alongside the proof for that
+    pub fn prove_padded(
+        claim: F,
+        mles: Vec<Vec<F>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> (Self, Vec<usize>) {
+        let lengths = mles.iter().map(|mle| mle.len()).collect();
+        let max_len = mles.iter().map(|mle| mle.len()).max().unwrap();
+        let padded = mles
+            .iter()
+            .map(|mle| {
+                let mut mle = pad_next_power_of_two(mle);
+                mle.resize(max_len.next_power_of_two(), F::ZERO);
+                mle
+            })
+            .collect();
+        (Self::prove(claim, padded, transcript), lengths)
+    }
+
+    // The product of the final terms, which for a product-of-MLEs proof is
+    // what `verify` returns. It only means something once the caller has
+    // checked every final term against an opening of its MLE: the prover
+    // picks final_terms freely, so the product alone is easy to match
+    pub fn final_product(&self) -> F {
+        self.final_terms.iter().product()
+    }
+
+    // For a proof from `prove_padded` over tables of the given lengths, the
+    // opening each original table must satisfy. The extended table at r is
+    // the original at r's trailing coordinates times prod (1 - r_j) over the
+    // leading ones. Call it only after `verify` has accepted the proof
+    pub fn unpadded_openings(&self, lengths: &[usize]) -> Result<Vec<Opening<F>>, SumcheckError> {
+        if self.final_terms.len() != lengths.len() {
+            return Err(SumcheckError::FinalTermCountMismatch {
+                got: self.final_terms.len(),
+                expected: lengths.len(),
+            });
+        }
+        let max_len = lengths
+            .iter()
+            .max()
+            .map_or(1, |len| len.next_power_of_two());
+        let rounds = max_len.ilog2() as usize;
+        if self.rounds != rounds || self.rands.len() != rounds {
+            return Err(SumcheckError::RoundCountMismatch {
+                got: self.rounds,
+                expected: rounds,
+            });
+        }
+        lengths
+            .iter()
+            .zip(&self.final_terms)
+            .enumerate()
+            .map(|(table, (&len, &term))| {
+                let extra = rounds - len.next_power_of_two().ilog2() as usize;
+                let (leading, point) = self.rands.split_at(extra);
+                let factor: F = leading.iter().map(|&r| F::ONE - r).product();
+                let inverse = factor
+                    .inverse()
+                    .ok_or(SumcheckError::ZeroPaddingFactor { table })?;
+                Ok((point.to_vec(), term * inverse))
+            })
+            .collect()
+    }
+
+    // Proves claim = sum of comb(mles at x) over the hypercube. comb must be
+    // a polynomial of total degree at most `degree` in the MLE values; the
+    // verifier gets the folded values in final_terms and applies comb itself
+    pub fn prove_with<Comb: Fn(&[F]) -> F + Sync>(
+        claim: F,
+        mles: Vec<Vec<F>>,
+        degree: usize,
+        comb: Comb,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        Self::prove_inner(
+            claim,
+            mles,
+            degree,
+            comb,
+            false,
+            &mut |_, _, _| {},
+            transcript,
+        )
+    }
+
+    // Like `prove`, calling on_round(i, points, mles) after round i's
+    // polynomial is derived, with its evaluations at 0..=degree and the
+    // tables it was derived from (2^(rounds - i) entries each, not yet
+    // folded by that round's challenge)
+    pub fn prove_with_callback(
+        claim: F,
+        mles: Vec<Vec<F>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+        mut on_round: impl FnMut(usize, &[F], &[Vec<F>]),
+    ) -> Self {
+        for mle in &mles {
+            assert_power_of_two(mle, "sumcheck");
+        }
+        let degree = mles.len();
+        Self::prove_inner(
+            claim,
+            mles,
+            degree,
+            |values| values.iter().product(),
+            false,
+            &mut on_round,
+            transcript,
+        )
+    }
+
+    // Like `prove`, but each round sends degree evaluations instead of
+    // degree + 1
+    pub fn prove_compressed(
+        claim: F,
+        mles: Vec<Vec<F>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        let degree = mles.len();
+        Self::prove_inner(
+            claim,
+            mles,
+            degree,
+            |values| values.iter().product(),
+            true,
+            &mut |_, _, _| {},
+            transcript,
+        )
+    }
+
+    fn prove_inner<Comb: Fn(&[F]) -> F + Sync>(
         claim: F,
         mut mles: Vec<Vec<F>>,
+        degree: usize,
+        comb: Comb,
+        compressed: bool,
+        on_round: &mut impl FnMut(usize, &[F], &[Vec<F>]),
         transcript: &mut impl ProtocolTranscript<F>,
     ) -> Self {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        transcript.append_version();
         transcript.append_scalar(b"sumcheck_claim", &claim);
-        let degree = mles.len();
-        transcript.append_scalar(b"sumcheck_degree", &F::from(degree as u64));
+        transcript.append_usize(b"sumcheck_degree", degree);
         let mle_len = mles[0].len();
+        assert!(
+            mles.iter().all(|mle| mle.len() == mle_len),
+            "sumcheck tables must all have the same length, got {:?}; see prove_padded",
+            mles.iter().map(|mle| mle.len()).collect::<Vec<_>>()
+        );
         let rounds = mle_len.ilog2() as usize;
-        transcript.append_scalar(b"sumcheck_rounds", &F::from(rounds as u64));
+        transcript.append_usize(b"sumcheck_rounds", rounds);
         let mut rs = vec![F::ZERO; rounds];
         let mut last_claim = claim;
-        let points = derive_points(&mles, last_claim);
-        transcript.append_points(b"sumcheck_points", &points);
-        let mut polys = vec![points];
-        for i in 1..rounds {
+        let label = points_label(compressed);
+        // With no variables there are no rounds, and the claim is checked
+        // directly against the single-entry tables
+        let mut polys = vec![];
+        let domain = round_domain(degree);
+        if rounds > 0 {
+            let points = derive_points(&mles, &domain, &comb, last_claim);
+            on_round(0, &points, &mles);
+            transcript.append_points(label, &transmitted(&points, compressed));
+            polys.push(points);
+            for i in 1..rounds {
+                let r = transcript.challenge_scalar(b"sumcheck_challenge");
+                for mle in mles.iter_mut() {
+                    fix_variable_in_place(mle, r);
+                }
+                last_claim = eval_ule(&polys[i - 1], r);
+                let points = derive_points(&mles, &domain, &comb, last_claim);
+                on_round(i, &points, &mles);
+                transcript.append_points(label, &transmitted(&points, compressed));
+                polys.push(points);
+                rs[i - 1] = r;
+            }
             let r = transcript.challenge_scalar(b"sumcheck_challenge");
-            for j in 0..mles.len() {
-                mles[j] = set_variable(&mles[j], r);
+            rs[rounds - 1] = r;
+            for mle in mles.iter_mut() {
+                fix_variable_in_place(mle, r);
             }
-            last_claim = eval_ule(&polys[i - 1], r);
-            let points = derive_points(&mles, last_claim);
-            transcript.append_points(b"sumcheck_points", &points);
-            polys.push(points);
-            rs[i - 1] = r;
         }
-        let r = transcript.challenge_scalar(b"sumcheck_challenge");
-        rs[rounds - 1] = r;
-        let finals = mles.iter().map(|mle| set_variable(mle, r)[0]).collect();
-        SumcheckProof {
-            polynomials: polys,
+        let finals = mles.iter().map(|mle| mle[0]).collect();
+        let proof = SumcheckProof {
+            polynomials: polys
+                .iter()
+                .map(|points| transmitted(points, compressed))
+                .collect(),
             final_terms: finals,
             rands: rs,
-            degree: degree,
-            rounds: rounds,
-            claim: claim,
+            degree,
+            rounds,
+            claim,
+            compressed,
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(start, comb);
+        proof
+    }
+
+    // Replays the verifier from the transcript state the prover started with
+    // and panics if it disagrees with what the prover produced
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, mut transcript: impl ProtocolTranscript<F>, comb: impl Fn(&[F]) -> F) {
+        crate::selfverify::check("sumcheck", || {
+            matches!(
+                self.verify(&mut transcript),
+                Ok((rands, eval)) if rands == self.rands && eval == comb(&self.final_terms)
+            )
+        });
+    }
+
+    // Verifies the proof is for the sum the caller expects, not just that it
+    // is internally consistent
+    pub fn verify_claim(
+        &self,
+        expected_claim: F,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, F), SumcheckError> {
+        if self.claim != expected_claim {
+            return Err(SumcheckError::UnexpectedClaim);
+        }
+        self.verify(transcript)
+    }
+
+    // Checks the proof's shape (round count and polynomial lengths) without
+    // any field arithmetic or transcript interaction, so malformed proofs are
+    // cheap to reject
+    pub fn check_structure(&self) -> Result<(), SumcheckError> {
+        if self.degree == 0 {
+            return Err(SumcheckError::InvalidDegree { got: 0 });
+        }
+        if self.polynomials.len() != self.rounds {
+            return Err(SumcheckError::RoundCountMismatch {
+                got: self.polynomials.len(),
+                expected: self.rounds,
+            });
         }
+        let expected = self.transmitted_len();
+        for (round, poly) in self.polynomials.iter().enumerate() {
+            if poly.len() != expected {
+                return Err(SumcheckError::DegreeMismatch {
+                    round,
+                    got: poly.len(),
+                    expected,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Alias of `verify`, which runs `check_structure` before any field arithmetic
+    pub fn verify_structure_first(
+        &self,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, F), SumcheckError> {
+        self.verify(transcript)
+    }
+
+    // Like `verify`, for callers that know how many rounds and what degree
+    // the proof must have rather than taking the prover's word for it
+    pub fn verify_shape(
+        &self,
+        rounds: usize,
+        degree: usize,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, F), SumcheckError> {
+        if self.rounds != rounds {
+            return Err(SumcheckError::RoundCountMismatch {
+                got: self.rounds,
+                expected: rounds,
+            });
+        }
+        if self.degree != degree {
+            return Err(SumcheckError::UnexpectedDegree {
+                got: self.degree,
+                expected: degree,
+            });
+        }
+        self.verify(transcript)
+    }
+
+    // Decodes a compressed proof and checks it against the public claim and
+    // shape, for a standalone verifier that only ever sees the bytes
+    pub fn verify_from_bytes(
+        bytes: &[u8],
+        expected_claim: F,
+        rounds: usize,
+        degree: usize,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, F), SumcheckError> {
+        let proof = Self::deserialize_compressed(bytes).map_err(|_| SumcheckError::Undecodable)?;
+        if proof.claim != expected_claim {
+            return Err(SumcheckError::UnexpectedClaim);
+        }
+        proof.verify_shape(rounds, degree, transcript)
+    }
+
+    fn transmitted_len(&self) -> usize {
+        if self.compressed {
+            self.degree
+        } else {
+            self.degree.saturating_add(1)
+        }
+    }
+
+    // Round i's evaluations at 0..=degree, filling in the point at 1 from
+    // the round's claim when the proof is compressed
+    fn round_polynomial(&self, i: usize, claim: F) -> Vec<F> {
+        let mut points = self.polynomials[i].clone();
+        if self.compressed {
+            points.insert(1, claim - points[0]);
+        }
+        points
+    }
+
+    // Panicking form of `verify` for callers that treat a bad proof as a bug
+    pub fn verify_unchecked(&self, transcript: &mut impl ProtocolTranscript<F>) -> (Vec<F>, F) {
+        self.verify(transcript)
+            .expect("sumcheck verification failed")
     }
 
-    pub fn verify(&self, transcript: &mut impl ProtocolTranscript<F>) -> (Vec<F>, F) {
+    pub fn verify(
+        &self,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, F), SumcheckError> {
+        // rounds and degree come from the prover, so nothing is sized by
+        // them until they agree with the polynomials actually sent
+        self.check_structure()?;
         let mut rs = vec![F::ZERO; self.rounds];
+        transcript.append_version();
         transcript.append_scalar(b"sumcheck_claim", &self.claim);
-        transcript.append_scalar(b"sumcheck_degree", &F::from(self.degree as u64));
-        transcript.append_scalar(b"sumcheck_rounds", &F::from(self.rounds as u64));
-        transcript.append_points(b"sumcheck_points", &self.polynomials[0]);
-        assert_eq!(self.claim, self.polynomials[0][0] + self.polynomials[0][1]);
-        for i in 1..self.polynomials.len() {
-            let r = transcript.challenge_scalar(b"sumcheck_challenge");
-            assert_eq!(self.polynomials[i].len(), self.degree + 1);
-            assert_eq!(
-                eval_ule(&self.polynomials[i - 1], r),
-                self.polynomials[i][0] + self.polynomials[i][1]
-            );
-            rs[i - 1] = r;
-            transcript.append_points(b"sumcheck_points", &self.polynomials[i]);
-        }
+        transcript.append_usize(b"sumcheck_degree", self.degree);
+        transcript.append_usize(b"sumcheck_rounds", self.rounds);
         if self.rounds == 0 {
-            (rs, self.claim)
-        } else {
+            return Ok((rs, self.claim));
+        }
+        let domain = BarycentricDomain::new(self.degree + 1);
+        let label = points_label(self.compressed);
+        let mut last_claim = self.claim;
+        let mut poly = vec![];
+        for i in 0..self.rounds {
+            if i > 0 {
+                let r = transcript.challenge_scalar(b"sumcheck_challenge");
+                last_claim = domain.evaluate(&poly, r);
+                rs[i - 1] = r;
+            }
+            poly = self.round_polynomial(i, last_claim);
+            if last_claim != poly[0] + poly[1] {
+                return Err(SumcheckError::ClaimMismatch { round: i });
+            }
+            transcript.append_points(label, &self.polynomials[i]);
+        }
+        let r = transcript.challenge_scalar(b"sumcheck_challenge");
+        rs[self.rounds - 1] = r;
+        Ok((rs, domain.evaluate(&poly, r)))
+    }
+
+    // Proves claim = sum over x of eq(z, x) * prod_k mles[k](x) without an eq
+    // table in mles. Each round's polynomial is eq(z_i, X) times the sum
+    // over the remaining eq(z_>i, .) only, scaled by the eq factor of the
+    // variables already bound. The proof reads like `prove` over
+    // [chis(z)] ++ mles except that final_terms leaves out the eq value
+    pub fn prove_with_eq(
+        claim: F,
+        z: &[F],
+        mut mles: Vec<Vec<F>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let degree = mles.len() + 1;
+        let rounds = z.len();
+        assert!(mles.iter().all(|mle| mle.len() == 1 << rounds));
+        transcript.append_version();
+        transcript.append_scalar(b"sumcheck_claim", &claim);
+        transcript.append_usize(b"sumcheck_degree", degree);
+        transcript.append_usize(b"sumcheck_rounds", rounds);
+        let mut rs = vec![];
+        let mut polys = vec![];
+        let mut scale = F::ONE;
+        for i in 0..rounds {
+            if i > 0 {
+                let r = transcript.challenge_scalar(b"sumcheck_challenge");
+                for mle in mles.iter_mut() {
+                    fix_variable_in_place(mle, r);
+                }
+                scale *= eval_eq(&z[i - 1..i], &[r]);
+                rs.push(r);
+            }
+            let eq_rest = chis(&z[i + 1..]);
+            let half = eq_rest.len();
+            let points: Vec<F> = (0..degree + 1)
+                .map(|j| {
+                    let t = F::from(j as u64);
+                    let rest: F = (0..half)
+                        .map(|x| {
+                            mles.iter()
+                                .map(|mle| mle[x] * (F::ONE - t) + mle[x + half] * t)
+                                .product::<F>()
+                                * eq_rest[x]
+                        })
+                        .sum();
+                    scale * eval_eq(&z[i..i + 1], &[t]) * rest
+                })
+                .collect();
+            transcript.append_points(b"sumcheck_points", &points);
+            polys.push(points);
+        }
+        if rounds > 0 {
             let r = transcript.challenge_scalar(b"sumcheck_challenge");
-            let final_eval = eval_ule(&self.polynomials[self.rounds - 1], r);
-            rs[self.rounds - 1] = r;
-            (rs, final_eval)
+            for mle in mles.iter_mut() {
+                fix_variable_in_place(mle, r);
+            }
+            rs.push(r);
+        }
+        let proof = SumcheckProof {
+            polynomials: polys,
+            final_terms: mles.iter().map(|mle| mle[0]).collect(),
+            rands: rs,
+            degree,
+            rounds,
+            claim,
+            compressed: false,
+        };
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(start, |terms: &[F]| {
+            eval_eq(z, &proof.rands) * terms.iter().product::<F>()
+        });
+        proof
+    }
+
+    // Verifies a proof from `prove_with_eq`, multiplying eq(z, r) back in at
+    // the final point. Returns the point and the MLE evaluations there
+    pub fn verify_with_eq(
+        &self,
+        z: &[F],
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, Vec<F>), SumcheckError> {
+        if z.len() != self.rounds {
+            return Err(SumcheckError::RoundCountMismatch {
+                got: self.rounds,
+                expected: z.len(),
+            });
+        }
+        let (rands, final_eval) = self.verify(transcript)?;
+        if final_eval != eval_eq(z, &rands) * self.final_product() {
+            return Err(SumcheckError::FinalEvalMismatch);
         }
+        Ok((rands, self.final_terms.clone()))
+    }
+
+    // Proves several product-of-MLE claims at once as a single sumcheck over
+    // sum_i rho^i * claims[i]. All instances must have the same number of
+    // variables; they may have different numbers of MLEs
+    pub fn prove_batched(
+        claims: &[F],
+        instances: Vec<Vec<Vec<F>>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        assert_eq!(claims.len(), instances.len());
+        let num_vars = instances[0][0].len();
+        assert!(
+            instances.iter().flatten().all(|mle| mle.len() == num_vars),
+            "batched instances must share the same variable count"
+        );
+        let rho = batch_challenge(claims, transcript);
+        let powers = powers(rho, claims.len());
+        let claim = claims.iter().zip(&powers).map(|(&c, &p)| c * p).sum();
+        let sizes: Vec<usize> = instances.iter().map(|mles| mles.len()).collect();
+        let degree = *sizes.iter().max().unwrap();
+        let comb = |values: &[F]| {
+            split_terms(values, &sizes)
+                .zip(&powers)
+                .map(|(terms, &p)| p * terms.iter().product::<F>())
+                .sum()
+        };
+        let mles = instances.into_iter().flatten().collect();
+        Self::prove_with(claim, mles, degree, comb, transcript)
+    }
+
+    // Verifies a proof from `prove_batched`, where sizes[i] is the number of
+    // MLEs in instance i. Returns the shared point and each instance's MLE
+    // evaluations there, for the caller to check against its commitments
+    pub fn verify_batched(
+        &self,
+        claims: &[F],
+        sizes: &[usize],
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<F>, Vec<Vec<F>>), SumcheckError> {
+        assert_eq!(claims.len(), sizes.len());
+        let expected = sizes.iter().sum::<usize>();
+        if self.final_terms.len() != expected {
+            return Err(SumcheckError::FinalTermCountMismatch {
+                got: self.final_terms.len(),
+                expected,
+            });
+        }
+        let rho = batch_challenge(claims, transcript);
+        let powers = powers(rho, claims.len());
+        let claim: F = claims.iter().zip(&powers).map(|(&c, &p)| c * p).sum();
+        if self.claim != claim {
+            return Err(SumcheckError::UnexpectedClaim);
+        }
+        let (rands, final_eval) = self.verify(transcript)?;
+        let openings: Vec<Vec<F>> = split_terms(&self.final_terms, sizes)
+            .map(|terms| terms.to_vec())
+            .collect();
+        let combined: F = openings
+            .iter()
+            .zip(&powers)
+            .map(|(terms, &p)| p * terms.iter().product::<F>())
+            .sum();
+        if final_eval != combined {
+            return Err(SumcheckError::FinalEvalMismatch);
+        }
+        Ok((rands, openings))
+    }
+}
+
+// Statement: the claimed sum. Witness: the MLEs whose product is summed
+impl<F: PrimeField> Provable<F> for SumcheckProof<F> {
+    type Statement = F;
+    type Witness = Vec<Vec<F>>;
+    type Proof = Self;
+    type Output = (Vec<F>, F);
+
+    fn prove(claim: &F, mles: Vec<Vec<F>>, transcript: &mut impl ProtocolTranscript<F>) -> Self {
+        SumcheckProof::prove(*claim, mles, transcript)
+    }
+
+    fn verify(
+        claim: &F,
+        proof: &Self,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Option<(Vec<F>, F)> {
+        proof.verify_claim(*claim, transcript).ok()
     }
 }
 
+fn points_label(compressed: bool) -> &'static [u8] {
+    if compressed {
+        b"sumcheck_compressed_points"
+    } else {
+        b"sumcheck_points"
+    }
+}
+
+fn transmitted<F: PrimeField>(points: &[F], compressed: bool) -> Vec<F> {
+    if compressed {
+        [&points[..1], &points[2..]].concat()
+    } else {
+        points.to_vec()
+    }
+}
+
+fn batch_challenge<F: PrimeField>(claims: &[F], transcript: &mut impl ProtocolTranscript<F>) -> F {
+    transcript.append_points(b"sumcheck_batch_claims", claims);
+    transcript.challenge_scalar_nonzero(b"sumcheck_batch_rho")
+}
+
+fn powers<F: PrimeField>(rho: F, n: usize) -> Vec<F> {
+    core::iter::successors(Some(F::ONE), |&p| Some(p * rho))
+        .take(n)
+        .collect()
+}
+
+fn split_terms<'a, F>(terms: &'a [F], sizes: &'a [usize]) -> impl Iterator<Item = &'a [F]> {
+    sizes.iter().scan(0, move |start, &size| {
+        *start += size;
+        Some(&terms[*start - size..*start])
+    })
+}
+
+// A product-of-MLEs sumcheck whose witness stays in the base field F while
+// the challenges, round polynomials and folded tables live in an extension
+// E, cutting the soundness error from degree * rounds / |F| to
+// degree * rounds / |E|. Each challenge is extension_degree base-field
+// challenges, and E values are absorbed as their base-field coordinates
+#[derive(Clone)]
+pub struct ExtensionSumcheckProof<F: PrimeField, E: Field<BasePrimeField = F>> {
+    pub polynomials: Vec<Vec<E>>,
+    pub rands: Vec<E>,
+    pub final_terms: Vec<E>,
+    pub degree: usize,
+    pub rounds: usize,
+    pub claim: E,
+}
+
+impl<F: PrimeField, E: Field<BasePrimeField = F>> ExtensionSumcheckProof<F, E> {
+    pub fn prove(claim: F, mles: Vec<Vec<F>>, transcript: &mut impl ProtocolTranscript<F>) -> Self {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let (degree, rounds) = ext_shape(&mles);
+        let lifted_claim = E::from_base_prime_field(claim);
+        absorb_ext_header(lifted_claim, degree, rounds, transcript);
+        if rounds == 0 {
+            let tables = mles.iter().map(|mle| lift(mle)).collect();
+            let proof =
+                Self::prove_rounds(lifted_claim, tables, vec![], vec![], degree, transcript);
+            #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+            proof.self_verify(start);
+            return proof;
+        }
+        // The first round runs over F; folding by its challenge lifts the
+        // tables into E for the rest
+        let points: Vec<E> = lift(&derive_points(
+            &mles,
+            &round_domain(degree),
+            &product::<F>,
+            claim,
+        ));
+        absorb_ext(b"sumcheck_ext_points", &points, transcript);
+        let r = ext_challenge(b"sumcheck_challenge", transcript);
+        let tables = mles
+            .iter()
+            .map(|mle| {
+                let (lo, hi) = mle.split_at(mle.len() / 2);
+                lo.iter()
+                    .zip(hi)
+                    .map(|(&a, &b)| {
+                        E::from_base_prime_field(a) + r * E::from_base_prime_field(b - a)
+                    })
+                    .collect()
+            })
+            .collect();
+        let proof = Self::prove_rounds(
+            lifted_claim,
+            tables,
+            vec![points],
+            vec![r],
+            degree,
+            transcript,
+        );
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(start);
+        proof
+    }
+
+    // `prove` over tables already in E, as when one of them is the eq table
+    // of an extension point
+    pub fn prove_lifted(
+        claim: E,
+        mles: Vec<Vec<E>>,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        let start = transcript.clone();
+        let (degree, rounds) = ext_shape(&mles);
+        absorb_ext_header(claim, degree, rounds, transcript);
+        let proof = Self::prove_rounds(claim, mles, vec![], vec![], degree, transcript);
+        #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+        proof.self_verify(start);
+        proof
+    }
+
+    // Replays the verifier from the transcript state the prover started with
+    // and panics if it disagrees with what the prover produced
+    #[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+    fn self_verify(&self, mut transcript: impl ProtocolTranscript<F>) {
+        crate::selfverify::check("extension sumcheck", || {
+            matches!(
+                self.verify(&mut transcript),
+                Ok((rands, eval)) if rands == self.rands && eval == product(&self.final_terms)
+            )
+        });
+    }
+
+    // Runs the rounds left after the ones already in polys and rands, over
+    // tables folded by those rands
+    fn prove_rounds(
+        claim: E,
+        mut tables: Vec<Vec<E>>,
+        mut polynomials: Vec<Vec<E>>,
+        mut rands: Vec<E>,
+        degree: usize,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Self {
+        let domain = round_domain(degree);
+        let mut last_claim = match (polynomials.last(), rands.last()) {
+            (Some(points), Some(&r)) => eval_ule_field(points, r),
+            _ => claim,
+        };
+        while tables[0].len() > 1 {
+            let points = derive_points(&tables, &domain, &product::<E>, last_claim);
+            absorb_ext(b"sumcheck_ext_points", &points, transcript);
+            let r = ext_challenge(b"sumcheck_challenge", transcript);
+            for table in tables.iter_mut() {
+                fix_variable_in_place(table, r);
+            }
+            last_claim = eval_ule_field(&points, r);
+            polynomials.push(points);
+            rands.push(r);
+        }
+        Self {
+            rounds: rands.len(),
+            polynomials,
+            rands,
+            final_terms: tables.iter().map(|table| table[0]).collect(),
+            degree,
+            claim,
+        }
+    }
+
+    // Returns the point and the product of the final terms, which the caller
+    // must check against openings of the MLEs there
+    pub fn verify(
+        &self,
+        transcript: &mut impl ProtocolTranscript<F>,
+    ) -> Result<(Vec<E>, E), SumcheckError> {
+        absorb_ext_header(self.claim, self.degree, self.rounds, transcript);
+        if self.degree == 0 {
+            return Err(SumcheckError::InvalidDegree { got: 0 });
+        }
+        if self.polynomials.len() != self.rounds {
+            return Err(SumcheckError::RoundCountMismatch {
+                got: self.polynomials.len(),
+                expected: self.rounds,
+            });
+        }
+        let mut last_claim = self.claim;
+        let mut rs = vec![];
+        for (i, poly) in self.polynomials.iter().enumerate() {
+            if poly.len() != self.degree + 1 {
+                return Err(SumcheckError::DegreeMismatch {
+                    round: i,
+                    got: poly.len(),
+                    expected: self.degree + 1,
+                });
+            }
+            if last_claim != poly[0] + poly[1] {
+                return Err(SumcheckError::ClaimMismatch { round: i });
+            }
+            absorb_ext(b"sumcheck_ext_points", poly, transcript);
+            let r = ext_challenge(b"sumcheck_challenge", transcript);
+            last_claim = eval_ule_field(poly, r);
+            rs.push(r);
+        }
+        let product: E = self.final_terms.iter().product();
+        if self.final_terms.len() != self.degree || last_claim != product {
+            return Err(SumcheckError::FinalEvalMismatch);
+        }
+        Ok((rs, product))
+    }
+}
+
+fn product<T: Field>(values: &[T]) -> T {
+    values.iter().product()
+}
+
+pub(crate) fn lift<F: PrimeField, E: Field<BasePrimeField = F>>(values: &[F]) -> Vec<E> {
+    values
+        .iter()
+        .map(|&v| E::from_base_prime_field(v))
+        .collect()
+}
+
+// (degree, rounds) of a product sumcheck over mles
+fn ext_shape<T>(mles: &[Vec<T>]) -> (usize, usize) {
+    for mle in mles {
+        assert_power_of_two(mle, "extension sumcheck");
+    }
+    let mle_len = mles[0].len();
+    assert!(
+        mles.iter().all(|mle| mle.len() == mle_len),
+        "extension sumcheck tables must all have the same length, got {:?}",
+        mles.iter().map(|mle| mle.len()).collect::<Vec<_>>()
+    );
+    (mles.len(), mle_len.ilog2() as usize)
+}
+
+fn absorb_ext_header<F: PrimeField, E: Field<BasePrimeField = F>>(
+    claim: E,
+    degree: usize,
+    rounds: usize,
+    transcript: &mut impl ProtocolTranscript<F>,
+) {
+    transcript.append_version();
+    absorb_ext(b"sumcheck_claim", &[claim], transcript);
+    transcript.append_usize(b"sumcheck_degree", degree);
+    transcript.append_usize(b"sumcheck_rounds", rounds);
+}
+
+pub(crate) fn absorb_ext<F: PrimeField, E: Field<BasePrimeField = F>>(
+    label: &'static [u8],
+    points: &[E],
+    transcript: &mut impl ProtocolTranscript<F>,
+) {
+    let coords: Vec<F> = points
+        .iter()
+        .flat_map(|p| p.to_base_prime_field_elements())
+        .collect();
+    transcript.append_points(label, &coords);
+}
+
+pub(crate) fn ext_challenge<F: PrimeField, E: Field<BasePrimeField = F>>(
+    label: &'static [u8],
+    transcript: &mut impl ProtocolTranscript<F>,
+) -> E {
+    let coords = transcript.challenge_scalars(label, E::extension_degree() as usize);
+    E::from_base_prime_field_elems(&coords).unwrap()
+}
+
 #[test]
 fn test() {
+    use crate::multilinear::{chis, eval_chis};
     use ark_curve25519::Fr;
+    use merlin::Transcript;
 
     let a = vec![
         Fr::from(9),
@@ -139,9 +1009,655 @@ fn test() {
     let mles = vec![a.clone(), b.clone()];
 
     let proof = SumcheckProof::prove(claim, mles, &mut transcript);
-    let (vrs, expected_eval) = proof.verify(&mut verify_transcript);
+    let (vrs, expected_eval) = proof.verify_unchecked(&mut verify_transcript);
 
     let rchis = chis(&vrs);
     let final_eval: Fr = eval_chis(&rchis, &a) * eval_chis(&rchis, &b);
     assert_eq!(final_eval, expected_eval);
 }
+
+#[test]
+fn test_verify_claim() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(2 * i as u64 + 3)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(proof.verify_claim(claim, &mut vtranscript).is_ok());
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        proof.verify_claim(claim + Fr::from(1), &mut vtranscript),
+        Err(SumcheckError::UnexpectedClaim)
+    );
+}
+
+#[test]
+fn test_verify_structure_first() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(2 * i as u64 + 3)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(proof.verify_structure_first(&mut vtranscript).is_ok());
+
+    let mut malformed = proof.clone();
+    malformed.polynomials[1].pop();
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        malformed.verify_structure_first(&mut vtranscript).err(),
+        Some(SumcheckError::DegreeMismatch {
+            round: 1,
+            got: 2,
+            expected: 3
+        })
+    );
+    // Rejected before anything was absorbed
+    let mut fresh = Transcript::new(b"test_transcript");
+    assert_eq!(
+        ProtocolTranscript::<Fr>::challenge_scalar(&mut vtranscript, b"c"),
+        ProtocolTranscript::<Fr>::challenge_scalar(&mut fresh, b"c")
+    );
+
+    let mut malformed = proof.clone();
+    malformed.polynomials.pop();
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        malformed.verify_structure_first(&mut vtranscript).err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 2,
+            expected: 3
+        })
+    );
+}
+
+#[test]
+fn test_hostile_shape() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let claim: Fr = a.iter().sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a], &mut transcript);
+    let verify =
+        |proof: &SumcheckProof<Fr>| proof.verify(&mut Transcript::new(b"test_transcript")).err();
+
+    // Fields a deserialized proof controls must not size allocations or
+    // overflow before they are checked
+    let mut hostile = proof.clone();
+    hostile.rounds = 1 << 60;
+    assert_eq!(
+        verify(&hostile),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 3,
+            expected: 1 << 60
+        })
+    );
+    let mut hostile = proof.clone();
+    hostile.degree = usize::MAX;
+    assert!(matches!(
+        verify(&hostile),
+        Some(SumcheckError::DegreeMismatch { round: 0, .. })
+    ));
+    hostile.rounds = 0;
+    hostile.polynomials.clear();
+    assert!(verify(&hostile).is_none());
+    let mut hostile = proof.clone();
+    hostile.compressed = true;
+    hostile.degree = 0;
+    assert_eq!(
+        verify(&hostile),
+        Some(SumcheckError::InvalidDegree { got: 0 })
+    );
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(proof.verify_shape(3, 1, &mut vtranscript).is_ok());
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        proof.verify_shape(3, 2, &mut vtranscript).err(),
+        Some(SumcheckError::UnexpectedDegree {
+            got: 1,
+            expected: 2
+        })
+    );
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        proof.verify_shape(2, 1, &mut vtranscript).err(),
+        Some(SumcheckError::RoundCountMismatch {
+            got: 3,
+            expected: 2
+        })
+    );
+}
+
+#[cfg(all(debug_assertions, feature = "debug_self_verify"))]
+#[test]
+#[should_panic(expected = "sumcheck self-verify failed")]
+fn test_self_verify_fires() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(2 * i as u64 + 3)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let start = transcript.clone();
+    let mut proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+    proof.polynomials[1][2] += Fr::from(1);
+    proof.self_verify(start, |t: &[Fr]| t[0] * t[1]);
+}
+
+#[test]
+fn test_prove_with() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+    use merlin::Transcript;
+
+    let mut rng = test_rng();
+    let a: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let b: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let c: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let comb = |v: &[Fr]| v[0] * v[1] - v[2];
+    let claim: Fr = (0..16).map(|i| comb(&[a[i], b[i], c[i]])).sum();
+
+    let mut transcript = Transcript::new(b"test");
+    let proof = SumcheckProof::prove_with(
+        claim,
+        vec![a.clone(), b.clone(), c.clone()],
+        2,
+        comb,
+        &mut transcript,
+    );
+    assert_eq!(proof.degree, 2);
+
+    let mut transcript = Transcript::new(b"test");
+    let (rands, final_eval) = proof.verify_unchecked(&mut transcript);
+    assert_eq!(final_eval, comb(&proof.final_terms));
+    assert_eq!(
+        proof.final_terms,
+        vec![
+            eval_mle(&rands, &a),
+            eval_mle(&rands, &b),
+            eval_mle(&rands, &c)
+        ]
+    );
+}
+
+#[test]
+fn test_batched() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+    use merlin::Transcript;
+
+    let mut rng = test_rng();
+    let mut table = || -> Vec<Fr> { (0..8).map(|_| Fr::rand(&mut rng)).collect() };
+    let (a, b, c, d) = (table(), table(), table(), table());
+    let claims = vec![
+        (0..8).map(|i| a[i] * b[i]).sum(),
+        (0..8).map(|i| c[i] * d[i]).sum(),
+    ];
+    let instances = vec![vec![a.clone(), b.clone()], vec![c.clone(), d.clone()]];
+
+    let mut transcript = Transcript::new(b"test");
+    let proof = SumcheckProof::prove_batched(&claims, instances, &mut transcript);
+
+    let mut transcript = Transcript::new(b"test");
+    let (rands, openings) = proof
+        .verify_batched(&claims, &[2, 2], &mut transcript)
+        .unwrap();
+    let expected: Vec<Vec<Fr>> = [[&a, &b], [&c, &d]]
+        .iter()
+        .map(|mles| mles.iter().map(|mle| eval_mle(&rands, mle)).collect())
+        .collect();
+    assert_eq!(openings, expected);
+
+    let mut transcript = Transcript::new(b"test");
+    let wrong = vec![claims[0], claims[1] + Fr::from(1)];
+    assert_eq!(
+        proof.verify_batched(&wrong, &[2, 2], &mut transcript).err(),
+        Some(SumcheckError::UnexpectedClaim)
+    );
+
+    let mut transcript = Transcript::new(b"test");
+    assert_eq!(
+        proof
+            .verify_batched(&claims, &[2, 3], &mut transcript)
+            .err(),
+        Some(SumcheckError::FinalTermCountMismatch {
+            got: 4,
+            expected: 5
+        })
+    );
+}
+
+#[test]
+fn test_compressed() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+    use merlin::Transcript;
+
+    let mut rng = test_rng();
+    let a: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let b: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+
+    let mut transcript = Transcript::new(b"test");
+    let full = SumcheckProof::prove(claim, vec![a.clone(), b.clone()], &mut transcript);
+    let mut transcript = Transcript::new(b"test");
+    let proof = SumcheckProof::prove_compressed(claim, vec![a, b], &mut transcript);
+    assert!(proof.polynomials.iter().all(|poly| poly.len() == 2));
+    assert!(proof.check_structure().is_ok());
+
+    let mut transcript = Transcript::new(b"test");
+    let (rands, final_eval) = proof.verify_unchecked(&mut transcript);
+    assert_eq!(final_eval, proof.final_product());
+    assert_eq!(rands, proof.rands);
+    // Different transcript contents, so a different point
+    assert_ne!(rands, full.rands);
+
+    let mut tampered = proof.clone();
+    tampered.polynomials[1][1] += Fr::from(1);
+    let mut transcript = Transcript::new(b"test");
+    let (_, final_eval) = tampered.verify_unchecked(&mut transcript);
+    assert_ne!(final_eval, tampered.final_product());
+}
+
+#[test]
+fn test_tampered_round() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(3 * i as u64 + 2)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let mut proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+    proof.polynomials[1][0] += Fr::from(1);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert_eq!(
+        proof.verify(&mut vtranscript).err(),
+        Some(SumcheckError::ClaimMismatch { round: 1 })
+    );
+}
+
+#[test]
+fn test_zero_rounds() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let claim = Fr::from(6) * Fr::from(7);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(
+        claim,
+        vec![vec![Fr::from(6)], vec![Fr::from(7)]],
+        &mut transcript,
+    );
+    assert_eq!(proof.rounds, 0);
+    assert!(proof.polynomials.is_empty());
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (rands, final_eval) = proof.verify_structure_first(&mut vtranscript).unwrap();
+    assert!(rands.is_empty());
+    assert_eq!(final_eval, proof.final_product());
+}
+
+#[test]
+fn test_serialize() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 5)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(7 * i as u64 + 1)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a, b], &mut transcript);
+
+    let mut bytes = vec![];
+    proof.serialize_compressed(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), proof.compressed_size());
+    let decoded = SumcheckProof::<Fr>::deserialize_compressed(&bytes[..]).unwrap();
+    assert_eq!(decoded.polynomials, proof.polynomials);
+    assert_eq!(decoded.final_terms, proof.final_terms);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (rands, final_eval) = decoded.verify(&mut vtranscript).unwrap();
+    assert_eq!(rands, proof.rands);
+    assert_eq!(final_eval, proof.final_product());
+}
+
+#[test]
+fn test_prove_with_eq() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+    use merlin::Transcript;
+
+    let mut rng = test_rng();
+    let z: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+    let l: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let r: Vec<Fr> = (0..16).map(|_| Fr::rand(&mut rng)).collect();
+    let eq = chis(&z);
+    let claim: Fr = (0..16).map(|i| eq[i] * l[i] * r[i]).sum();
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let explicit = SumcheckProof::prove(claim, vec![eq, l.clone(), r.clone()], &mut transcript);
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof =
+        SumcheckProof::prove_with_eq(claim, &z, vec![l.clone(), r.clone()], &mut transcript);
+    assert_eq!(proof.polynomials, explicit.polynomials);
+    assert_eq!(proof.final_terms, explicit.final_terms[1..]);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (rands, terms) = proof.verify_with_eq(&z, &mut vtranscript).unwrap();
+    assert_eq!(terms, vec![eval_mle(&rands, &l), eval_mle(&rands, &r)]);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let mut other = z.clone();
+    other[0] += Fr::from(1);
+    assert_eq!(
+        proof.verify_with_eq(&other, &mut vtranscript).err(),
+        Some(SumcheckError::FinalEvalMismatch)
+    );
+}
+
+#[test]
+fn test_prove_padded() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+    use merlin::Transcript;
+
+    let mut rng = test_rng();
+    let a: Vec<Fr> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let b: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+    // b is zero beyond its length, so only a's first half contributes
+    let claim: Fr = (0..4).map(|i| a[i] * b[i]).sum();
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    let (proof, lengths) =
+        SumcheckProof::prove_padded(claim, vec![a.clone(), b.clone()], &mut transcript);
+    assert_eq!(proof.rounds, 3);
+    assert_eq!(lengths, [8, 4]);
+
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (_, final_eval) = proof.verify(&mut vtranscript).unwrap();
+    assert_eq!(final_eval, proof.final_product());
+    let openings = proof.unpadded_openings(&lengths).unwrap();
+    assert_eq!(openings[0].0.len(), 3);
+    assert_eq!(openings[1].0.len(), 2);
+    assert_eq!(openings[0].1, eval_mle(&openings[0].0, &a));
+    assert_eq!(openings[1].1, eval_mle(&openings[1].0, &b));
+
+    assert_eq!(
+        proof.unpadded_openings(&[8]),
+        Err(SumcheckError::FinalTermCountMismatch {
+            got: 2,
+            expected: 1
+        })
+    );
+    assert_eq!(
+        proof.unpadded_openings(&[16, 4]),
+        Err(SumcheckError::RoundCountMismatch {
+            got: 3,
+            expected: 4
+        })
+    );
+
+    // A leading challenge of 1 leaves b's table zeroed at the final point
+    let mut degenerate = proof.clone();
+    degenerate.rands[0] = Fr::from(1);
+    assert_eq!(
+        degenerate.unpadded_openings(&lengths),
+        Err(SumcheckError::ZeroPaddingFactor { table: 1 })
+    );
+}
+
+#[test]
+#[should_panic(expected = "same length")]
+fn test_unequal_lengths() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let mut transcript = Transcript::new(b"test_transcript");
+    SumcheckProof::prove(
+        Fr::from(0),
+        vec![vec![Fr::from(1); 8], vec![Fr::from(1); 4]],
+        &mut transcript,
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_derive_points_par() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    let comb = |values: &[Fr]| values.iter().product();
+    for n in [1, 5, 11, 14] {
+        let mles: Vec<Vec<Fr>> = (0..3)
+            .map(|_| (0..1 << n).map(|_| Fr::rand(&mut rng)).collect())
+            .collect();
+        let claim = Fr::rand(&mut rng);
+        assert_eq!(
+            derive_points_par(&mles, &round_domain(3), &comb, claim),
+            derive_points_serial(&mles, &round_domain(3), &comb, claim)
+        );
+    }
+}
+
+#[test]
+fn test_prime_field_bound() {
+    use crate::grandproduct::GrandProductProof;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    // Only compiles while the proofs ask for nothing beyond PrimeField
+    fn roundtrip<F: PrimeField>(witness: Vec<F>) -> bool {
+        let claim: F = witness.iter().product();
+        let mut transcript = Transcript::new(b"test_transcript");
+        let (proof, _) = GrandProductProof::prove(&witness, claim, &mut transcript);
+        let sumcheck = SumcheckProof::prove(witness.iter().sum(), vec![witness], &mut transcript);
+        let mut vtranscript = Transcript::new(b"test_transcript");
+        proof.verify(claim, &mut vtranscript).is_ok() && sumcheck.verify(&mut vtranscript).is_ok()
+    }
+
+    assert!(roundtrip((1..=8).map(|i| Fr::from(i as u64)).collect()));
+}
+
+#[test]
+#[should_panic(expected = "sumcheck: expected power-of-two table, got len 6")]
+fn test_prove_not_power_of_two() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let mle = vec![Fr::from(1u64); 6];
+    let mut transcript = Transcript::new(b"test_transcript");
+    SumcheckProof::prove(Fr::from(6u64), vec![mle], &mut transcript);
+}
+
+#[test]
+fn test_extension_sumcheck() {
+    use crate::fiatshamir::small_field::{Small, SmallExt};
+    use merlin::Transcript;
+
+    let a: Vec<Small> = [3u64, 14, 15, 92, 65, 35, 89, 79].map(Small::from).to_vec();
+    let b: Vec<Small> = [2u64, 71, 82, 81, 82, 84, 59, 4].map(Small::from).to_vec();
+    let claim: Small = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = ExtensionSumcheckProof::<Small, SmallExt>::prove(
+        claim,
+        vec![a.clone(), b.clone()],
+        &mut transcript,
+    );
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (r, eval) = proof.verify(&mut vtranscript).unwrap();
+
+    // Challenges come from all 97^2 elements rather than the 97 of F
+    assert!(r.iter().any(|r| r.c1 != Small::from(0u64)));
+    let lift = |v: &[Small]| -> SmallExt {
+        let eq = (0..v.len()).map(|i| {
+            (0..3)
+                .map(|j| match (i >> (2 - j)) & 1 {
+                    1 => r[j],
+                    _ => SmallExt::ONE - r[j],
+                })
+                .product::<SmallExt>()
+        });
+        eq.zip(v)
+            .map(|(e, &x)| e * SmallExt::from_base_prime_field(x))
+            .sum()
+    };
+    assert_eq!(eval, lift(&a) * lift(&b));
+
+    // A wrong claim, so the proof skips self-verify
+    let mut transcript = Transcript::new(b"test_transcript");
+    let wrong = crate::selfverify::skipped(|| {
+        ExtensionSumcheckProof::<Small, SmallExt>::prove(
+            claim + Small::from(1u64),
+            vec![a, b],
+            &mut transcript,
+        )
+    });
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    assert!(wrong.verify(&mut vtranscript).is_err());
+}
+
+#[test]
+fn test_final_product_needs_openings() {
+    use crate::multilinear::eval_mle;
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 2)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(3 * i as u64 + 1)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let mut proof = SumcheckProof::prove(claim, vec![a.clone(), b.clone()], &mut transcript);
+
+    // Scaling the terms by k and 1/k keeps the product and passes verify
+    let k = Fr::from(5u64);
+    proof.final_terms[0] *= k;
+    proof.final_terms[1] *= k.inverse().unwrap();
+    let mut vtranscript = Transcript::new(b"test_transcript");
+    let (r, final_eval) = proof.verify(&mut vtranscript).unwrap();
+    assert_eq!(final_eval, proof.final_product());
+    // Only checking each term against its opening catches it
+    assert_ne!(proof.final_terms[0], eval_mle(&r, &a));
+    assert_ne!(proof.final_terms[1], eval_mle(&r, &b));
+}
+
+#[test]
+fn test_prove_with_callback() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..16).map(|i| Fr::from(i as u64 + 1)).collect();
+    let b: Vec<Fr> = (0..16).map(|i| Fr::from(7 * i as u64 + 2)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut seen = vec![];
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove_with_callback(
+        claim,
+        vec![a.clone(), b.clone()],
+        &mut transcript,
+        |i, points, mles| seen.push((i, points.to_vec(), mles[0].len(), mles[1].len())),
+    );
+    assert_eq!(seen.len(), proof.rounds);
+    for (round, (i, points, a_len, b_len)) in seen.into_iter().enumerate() {
+        assert_eq!((i, a_len, b_len), (round, 16 >> round, 16 >> round));
+        assert_eq!(points, proof.polynomials[round]);
+    }
+
+    let mut plain_transcript = Transcript::new(b"test_transcript");
+    let plain = SumcheckProof::prove(claim, vec![a, b], &mut plain_transcript);
+    assert_eq!(plain.rands, proof.rands);
+}
+
+#[test]
+fn test_derive_points_domain() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    // Reference: every point summed directly, including the one at 1
+    fn naive(mles: &[Vec<Fr>], degree: usize) -> Vec<Fr> {
+        let half = mles[0].len() / 2;
+        (0..=degree)
+            .map(|j| {
+                let t = Fr::from(j as u64);
+                (0..half)
+                    .map(|i| {
+                        mles.iter()
+                            .map(|m| m[i] * (Fr::ONE - t) + m[i + half] * t)
+                            .product::<Fr>()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    let mut rng = test_rng();
+    let comb = |values: &[Fr]| values.iter().product();
+    for degree in [2, 3] {
+        let mles: Vec<Vec<Fr>> = (0..degree)
+            .map(|_| (0..16).map(|_| Fr::rand(&mut rng)).collect())
+            .collect();
+        let expected = naive(&mles, degree);
+        let claim = expected[0] + expected[1];
+        assert_eq!(
+            derive_points(&mles, &round_domain(degree), &comb, claim),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_verify_from_bytes() {
+    use ark_curve25519::Fr;
+    use merlin::Transcript;
+
+    let a: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64 + 2)).collect();
+    let b: Vec<Fr> = (0..8).map(|i| Fr::from(3 * i as u64 + 1)).collect();
+    let claim: Fr = a.iter().zip(&b).map(|(&a, &b)| a * b).sum();
+    let mut transcript = Transcript::new(b"test_transcript");
+    let proof = SumcheckProof::prove(claim, vec![a.clone(), b.clone()], &mut transcript);
+    let mut bytes = vec![];
+    proof.serialize_compressed(&mut bytes).unwrap();
+
+    let verify = |bytes: &[u8], claim| {
+        SumcheckProof::verify_from_bytes(
+            bytes,
+            claim,
+            3,
+            2,
+            &mut Transcript::new(b"test_transcript"),
+        )
+    };
+    let (r, eval) = verify(&bytes, claim).unwrap();
+    assert_eq!(
+        eval,
+        crate::multilinear::eval_mle(&r, &a) * crate::multilinear::eval_mle(&r, &b)
+    );
+    assert_eq!(
+        verify(&bytes, claim + Fr::from(1)),
+        Err(SumcheckError::UnexpectedClaim)
+    );
+    assert_eq!(
+        verify(&bytes[..bytes.len() - 1], claim),
+        Err(SumcheckError::Undecodable)
+    );
+}
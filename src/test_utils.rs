@@ -0,0 +1,55 @@
+use alloc::{vec, vec::Vec};
+use ark_ff::PrimeField;
+use ark_std::rand::Rng;
+
+// Random instances for benchmarks at sizes too large to write by hand
+
+pub fn random_mle<F: PrimeField>(num_vars: usize, rng: &mut impl Rng) -> Vec<F> {
+    (0..1 << num_vars).map(|_| F::rand(rng)).collect()
+}
+
+// Nonzero (row, col, value) entries, each cell kept with probability density
+pub fn random_sparse_matrix<F: PrimeField>(
+    rows: usize,
+    cols: usize,
+    density: f64,
+    rng: &mut impl Rng,
+) -> Vec<(usize, usize, F)> {
+    let mut entries = vec![];
+    for i in 0..rows * cols {
+        if rng.gen_bool(density) {
+            entries.push((i / cols, i % cols, random_nonzero(rng)));
+        }
+    }
+    entries
+}
+
+// A grand product witness with no zero entries, so its product is nonzero
+pub fn random_product_witness<F: PrimeField>(num_vars: usize, rng: &mut impl Rng) -> Vec<F> {
+    (0..1 << num_vars).map(|_| random_nonzero(rng)).collect()
+}
+
+fn random_nonzero<F: PrimeField>(rng: &mut impl Rng) -> F {
+    loop {
+        let x = F::rand(rng);
+        if !x.is_zero() {
+            return x;
+        }
+    }
+}
+
+#[test]
+fn test_random_instances() {
+    use ark_curve25519::Fr;
+    use ark_ff::Zero;
+    use ark_std::test_rng;
+
+    let mut rng = test_rng();
+    assert_eq!(random_mle::<Fr>(4, &mut rng).len(), 16);
+    let entries = random_sparse_matrix::<Fr>(8, 4, 0.5, &mut rng);
+    assert!(entries
+        .iter()
+        .all(|&(r, c, v)| r < 8 && c < 4 && !v.is_zero()));
+    let witness = random_product_witness::<Fr>(3, &mut rng);
+    assert!(!witness.iter().product::<Fr>().is_zero());
+}
@@ -1,33 +1,227 @@
-use ark_ff::{BigInteger, PrimeField};
+use alloc::{vec, vec::Vec};
+use ark_ff::{Field, PrimeField};
 
-pub fn eval_ule<F: PrimeField + From<i32>>(points: &[F], r: F) -> F {
-    // Check if r is in interpolated set
-    if F::ZERO <= r && r < F::from(points.len() as u64) {
-        return points[usize::from_le_bytes(
-            r.into_bigint().to_bytes_le()[0..8]
-                .try_into()
-                .expect("point too large"),
-        )];
+use crate::field_utils::batch_inverse;
+
+pub fn eval_ule<F: PrimeField>(points: &[F], r: F) -> F {
+    // Check if r is in interpolated set: r's integer must fit in the lowest
+    // limb and be in range, otherwise fall through to interpolation
+    let bigint = r.into_bigint();
+    let limbs = bigint.as_ref();
+    if limbs[1..].iter().all(|&l| l == 0) {
+        if let Ok(k) = usize::try_from(limbs[0]) {
+            if k < points.len() {
+                return points[k];
+            }
+        }
     }
-    let (mut total, mut multiplier, mut inversions) = (F::ZERO, F::ONE, F::ONE);
-    let length = points.len() as i32;
+    eval_ule_field(points, r)
+}
 
-    for k in 1..points.len() {
-        multiplier *= r - F::from(k as u64);
-        inversions *= F::from(0 - (k as i32))
+// `eval_ule` over any field, extensions included, without the in-set
+// shortcut that reads r's integer
+pub fn eval_ule_field<F: Field>(points: &[F], r: F) -> F {
+    // Over the nodes 0..n the denominator for node i is
+    // i! * (n - 1 - i)! * (-1)^(n - 1 - i), so no pairwise products needed
+    let n = points.len();
+    let mut factorials = vec![F::ONE; n.max(1)];
+    for k in 1..n {
+        factorials[k] = factorials[k - 1] * F::from(k as u64);
     }
+    let dens: Vec<F> = (0..n)
+        .map(|i| {
+            let den = factorials[i] * factorials[n - 1 - i];
+            if (n - 1 - i).is_multiple_of(2) {
+                den
+            } else {
+                -den
+            }
+        })
+        .collect();
+    let diffs: Vec<F> = (0..n).map(|k| r - F::from(k as u64)).collect();
+    lagrange_sum(points, &diffs, &dens)
+}
 
-    multiplier *= inversions.inverse().unwrap();
-    total += multiplier * points[0];
+// Lagrange interpolation over arbitrary distinct nodes. `eval_ule` is the
+// case domain = 0..values.len(), with the denominators in closed form
+pub fn eval_ule_domain<F: PrimeField>(domain: &[F], values: &[F], r: F) -> F {
+    assert_eq!(domain.len(), values.len());
+    for (i, a) in domain.iter().enumerate() {
+        assert!(
+            !domain[i + 1..].contains(a),
+            "interpolation domain has duplicate node at index {}",
+            i
+        );
+    }
+    let dens: Vec<F> = domain
+        .iter()
+        .enumerate()
+        .map(|(i, &xi)| {
+            domain
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &xj)| xi - xj)
+                .product()
+        })
+        .collect();
+    let diffs: Vec<F> = domain.iter().map(|&x| r - x).collect();
+    lagrange_sum(values, &diffs, &dens)
+}
 
-    for i in 1..length {
-        multiplier *= (r - F::from(i - 1))
-            * ((r - F::from(i)) * F::from(i)).inverse().unwrap()
-            * F::from(0 - (length - i));
+// sum_i values[i] * prod_{j != i} diffs[j] / dens[i], where diffs[j] is
+// r - x_j and dens[i] is prod_{j != i} (x_i - x_j). The numerators come from
+// prefix and suffix products, so r on a node needs no special case
+fn lagrange_sum<F: Field>(values: &[F], diffs: &[F], dens: &[F]) -> F {
+    let mut suffix = vec![F::ONE; diffs.len() + 1];
+    for j in (0..diffs.len()).rev() {
+        suffix[j] = suffix[j + 1] * diffs[j];
+    }
+    let mut prefix = F::ONE;
+    values
+        .iter()
+        .zip(diffs)
+        .zip(batch_inverse(dens))
+        .zip(&suffix[1..])
+        .map(|(((&y, &diff), den_inv), &after)| {
+            let term = y * prefix * after * den_inv;
+            prefix *= diff;
+            term
+        })
+        .sum()
+}
+
+// Barycentric weights for the domain 0..size, so a fixed-degree polynomial
+// can be evaluated at many r without redoing the denominators
+pub struct BarycentricDomain<F: PrimeField> {
+    weights: Vec<F>,
+}
+
+impl<F: PrimeField> BarycentricDomain<F> {
+    pub fn new(size: usize) -> Self {
+        let weights = (0..size)
+            .map(|i| {
+                (0..size)
+                    .filter(|&j| j != i)
+                    .map(|j| F::from(i as u64) - F::from(j as u64))
+                    .product::<F>()
+                    .inverse()
+                    .unwrap()
+            })
+            .collect();
+        BarycentricDomain { weights }
+    }
 
-        total += multiplier * points[i as usize]
+    pub fn size(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn evaluate(&self, points: &[F], r: F) -> F {
+        assert_eq!(points.len(), self.weights.len());
+        let diffs: Vec<F> = (0..points.len()).map(|i| r - F::from(i as u64)).collect();
+        if let Some(i) = diffs.iter().position(|d| d.is_zero()) {
+            return points[i];
+        }
+        // r is off the nodes, so every diff inverts, all in one batch
+        let numerator: F = diffs.iter().product();
+        self.weights
+            .iter()
+            .zip(batch_inverse(&diffs))
+            .zip(points)
+            .map(|((&w, d_inv), &p)| w * d_inv * p)
+            .sum::<F>()
+            * numerator
     }
-    return total;
+}
+
+// A polynomial by its coefficients, lowest degree first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnivariatePoly<F: PrimeField> {
+    pub coeffs: Vec<F>,
+}
+
+impl<F: PrimeField> UnivariatePoly<F> {
+    pub fn new(coeffs: Vec<F>) -> Self {
+        UnivariatePoly { coeffs }
+    }
+
+    // The interpolant of points at 0..points.len(), matching `eval_ule`
+    pub fn from_evals(points: &[F]) -> Self {
+        let n = points.len();
+        let mut coeffs = vec![F::ZERO; n];
+        for (i, &y) in points.iter().enumerate() {
+            // prod over j != i of (x - j) / (i - j)
+            let mut basis = vec![F::ONE];
+            let mut denominator = F::ONE;
+            for j in (0..n).filter(|&j| j != i) {
+                let node = F::from(j as u64);
+                let mut next = vec![F::ZERO; basis.len() + 1];
+                for (k, &c) in basis.iter().enumerate() {
+                    next[k] -= c * node;
+                    next[k + 1] += c;
+                }
+                basis = next;
+                denominator *= F::from(i as u64) - node;
+            }
+            let scale = y * denominator.inverse().unwrap();
+            for (coeff, b) in coeffs.iter_mut().zip(basis) {
+                *coeff += scale * b;
+            }
+        }
+        UnivariatePoly { coeffs }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.len().saturating_sub(1)
+    }
+
+    pub fn evaluate(&self, r: F) -> F {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(F::ZERO, |acc, &c| acc * r + c)
+    }
+
+    // Evaluations at 0..domain_size, the form `eval_ule` takes
+    pub fn to_evals(&self, domain_size: usize) -> Vec<F> {
+        (0..domain_size)
+            .map(|i| self.evaluate(F::from(i as u64)))
+            .collect()
+    }
+}
+
+impl<F: PrimeField> core::ops::Add for &UnivariatePoly<F> {
+    type Output = UnivariatePoly<F>;
+
+    fn add(self, other: Self) -> UnivariatePoly<F> {
+        let len = self.coeffs.len().max(other.coeffs.len());
+        let coeff = |p: &UnivariatePoly<F>, i: usize| p.coeffs.get(i).cloned().unwrap_or(F::ZERO);
+        UnivariatePoly::new((0..len).map(|i| coeff(self, i) + coeff(other, i)).collect())
+    }
+}
+
+impl<F: PrimeField> core::ops::Mul for &UnivariatePoly<F> {
+    type Output = UnivariatePoly<F>;
+
+    fn mul(self, other: Self) -> UnivariatePoly<F> {
+        if self.coeffs.is_empty() || other.coeffs.is_empty() {
+            return UnivariatePoly::new(vec![]);
+        }
+        let mut coeffs = vec![F::ZERO; self.coeffs.len() + other.coeffs.len() - 1];
+        for (i, &a) in self.coeffs.iter().enumerate() {
+            for (j, &b) in other.coeffs.iter().enumerate() {
+                coeffs[i + j] += a * b;
+            }
+        }
+        UnivariatePoly::new(coeffs)
+    }
+}
+
+// Evaluates the interpolant of points at every r, computing the
+// barycentric weights once
+pub fn eval_ule_many<F: PrimeField>(points: &[F], rs: &[F]) -> Vec<F> {
+    let domain = BarycentricDomain::new(points.len());
+    rs.iter().map(|&r| domain.evaluate(points, r)).collect()
 }
 
 #[test]
@@ -38,3 +232,164 @@ fn test_ule() {
     assert_eq!(eval_ule(&points, Fr::from(1)), Fr::from(1));
     assert_eq!(eval_ule(&points, Fr::from(3)), Fr::from(9))
 }
+
+#[test]
+fn test_ule_large_r() {
+    use ark_curve25519::Fr;
+    use ark_ff::{Field, One};
+    use ark_std::{test_rng, UniformRand};
+
+    let points = vec![Fr::from(3), Fr::from(1), Fr::from(4), Fr::from(1)];
+    // Low 8 bytes of 2^64 + 1 read as 1, but it is not in the set
+    let r = Fr::from(u64::MAX) + Fr::from(2);
+    let lagrange = |r: Fr| {
+        (0..points.len())
+            .map(|i| {
+                let basis: Fr = (0..points.len())
+                    .filter(|&j| j != i)
+                    .map(|j| {
+                        (r - Fr::from(j as u64))
+                            * (Fr::from(i as u64) - Fr::from(j as u64)).inverse().unwrap()
+                    })
+                    .product();
+                basis * points[i]
+            })
+            .sum::<Fr>()
+    };
+    assert_ne!(eval_ule(&points, r), points[1]);
+    assert_eq!(eval_ule(&points, r), lagrange(r));
+
+    let r = Fr::rand(&mut test_rng());
+    assert_eq!(eval_ule(&points, r), lagrange(r));
+    assert_eq!(eval_ule(&points, -Fr::one()), lagrange(-Fr::one()));
+}
+
+#[test]
+fn test_barycentric_domain() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for size in 2..=6 {
+        let domain = BarycentricDomain::new(size);
+        let points: Vec<Fr> = (0..size).map(|_| Fr::rand(&mut rng)).collect();
+        let r = Fr::rand(&mut rng);
+        assert_eq!(domain.evaluate(&points, r), eval_ule(&points, r));
+        assert_eq!(domain.evaluate(&points, Fr::from(1)), points[1]);
+    }
+}
+
+#[test]
+fn test_univariate_poly() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    // (1 + 2x)(3 + x + x^2) = 3 + 7x + 3x^2 + 2x^3
+    let a = UnivariatePoly::new(vec![Fr::from(1), Fr::from(2)]);
+    let b = UnivariatePoly::new(vec![Fr::from(3), Fr::from(1), Fr::from(1)]);
+    let product = &a * &b;
+    assert_eq!(
+        product,
+        UnivariatePoly::new(vec![Fr::from(3), Fr::from(7), Fr::from(3), Fr::from(2)])
+    );
+    let (ea, eb) = (a.to_evals(4), b.to_evals(4));
+    let pointwise: Vec<Fr> = ea.iter().zip(&eb).map(|(&x, &y)| x * y).collect();
+    assert_eq!(product.to_evals(4), pointwise);
+    assert_eq!(
+        (&a + &b).to_evals(4),
+        vec![Fr::from(4), Fr::from(8), Fr::from(14), Fr::from(22)]
+    );
+
+    let points = vec![Fr::from(5), Fr::from(1), Fr::from(8), Fr::from(2)];
+    let poly = UnivariatePoly::from_evals(&points);
+    assert_eq!(poly.degree(), 3);
+    assert_eq!(poly.to_evals(4), points);
+    let r = Fr::rand(&mut test_rng());
+    assert_eq!(poly.evaluate(r), eval_ule(&points, r));
+}
+
+#[test]
+fn test_ule_domain() {
+    use ark_curve25519::Fr;
+    use ark_ff::Field;
+
+    let domain = [Fr::from(2), Fr::from(5), Fr::from(9)];
+    let values = [Fr::from(4), Fr::from(1), Fr::from(7)];
+    let r = Fr::from(6);
+    let l0 = (r - domain[1])
+        * (r - domain[2])
+        * ((domain[0] - domain[1]) * (domain[0] - domain[2]))
+            .inverse()
+            .unwrap();
+    let l1 = (r - domain[0])
+        * (r - domain[2])
+        * ((domain[1] - domain[0]) * (domain[1] - domain[2]))
+            .inverse()
+            .unwrap();
+    let l2 = (r - domain[0])
+        * (r - domain[1])
+        * ((domain[2] - domain[0]) * (domain[2] - domain[1]))
+            .inverse()
+            .unwrap();
+    let manual = l0 * values[0] + l1 * values[1] + l2 * values[2];
+    assert_eq!(eval_ule_domain(&domain, &values, r), manual);
+    assert_eq!(eval_ule_domain(&domain, &values, Fr::from(5)), values[1]);
+
+    let consecutive = [Fr::from(0), Fr::from(1), Fr::from(2)];
+    assert_eq!(
+        eval_ule_domain(&consecutive, &values, r),
+        eval_ule(&values, r)
+    );
+}
+
+#[test]
+fn test_ule_matches_domain() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for n in 1..=8u64 {
+        let values: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let domain: Vec<Fr> = (0..n).map(Fr::from).collect();
+        let mut rs: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+        rs.extend([Fr::from(0), Fr::from(n - 1), Fr::from(n), -Fr::from(1)]);
+        for r in rs {
+            assert_eq!(eval_ule(&values, r), eval_ule_domain(&domain, &values, r));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "duplicate node")]
+fn test_ule_domain_duplicate() {
+    use ark_curve25519::Fr;
+
+    let domain = [Fr::from(2), Fr::from(5), Fr::from(2)];
+    eval_ule_domain(&domain, &[Fr::from(1); 3], Fr::from(7));
+}
+
+#[test]
+fn test_ule_many() {
+    use ark_curve25519::Fr;
+    use ark_std::{test_rng, UniformRand};
+
+    let mut rng = test_rng();
+    for len in 1..=6 {
+        let points: Vec<Fr> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+        let mut rs: Vec<Fr> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+        rs.push(Fr::from(0));
+        let expected: Vec<Fr> = rs.iter().map(|&r| eval_ule(&points, r)).collect();
+        assert_eq!(eval_ule_many(&points, &rs), expected);
+    }
+}
+
+#[test]
+fn eval_ule_far_point() {
+    use ark_curve25519::Fr;
+
+    // p(x) = 3x^2 + 2x + 5 through its values at 0, 1, 2
+    let p = |x: Fr| Fr::from(3u64) * x * x + Fr::from(2u64) * x + Fr::from(5u64);
+    let points: Vec<Fr> = (0..3u64).map(|x| p(Fr::from(x))).collect();
+    let r = Fr::from(u64::MAX);
+    assert_eq!(eval_ule(&points, r), p(r));
+}